@@ -0,0 +1,120 @@
+// Benchmarks of the simulation core, run them with `cargo bench -p particule_lib`.
+// `examples/bench_regressions.rs` fails when a run is slower than a saved baseline.
+// Every board is seeded so two runs measure the same work.
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use particule_lib::environment::Environment;
+use particule_lib::map::Map;
+use particule_lib::rng;
+use particule_lib::sma::Sma;
+use particule_lib::Coord;
+
+const SEED: u64 = 42;
+const SIDES: [i32; 3] = [64, 256, 512];
+// Share of the cells holding an agent, one shark for nine fish
+const DENSITIES: [f32; 3] = [0.1, 0.5, 0.9];
+
+// Board of `side` x `side` cells with agents spread at random
+fn board(side: i32, density: f32) -> Sma {
+    rng::seed(SEED);
+    let mut sma = Sma::new(side, side, 4, 10, 4, true);
+    let agents = ((side * side) as f32 * density) as u32;
+    sma.gen_agents(agents - agents / 10, agents / 10);
+    sma
+}
+
+// One tick from the same board every iteration, the population does not drift
+fn update_all(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_all");
+    for &side in SIDES.iter() {
+        for &density in DENSITIES.iter() {
+            let environment = board(side, density).environment;
+            group.throughput(Throughput::Elements((side * side) as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("{}x{}", side, side), density),
+                &environment,
+                |b, environment| {
+                    b.iter_batched_ref(
+                        || environment.clone(),
+                        Environment::update_all,
+                        BatchSize::LargeInput,
+                    )
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn neighbors(c: &mut Criterion) {
+    let environment = board(256, 0.5).environment;
+    let coords: Vec<Coord> = (0..256).map(|i| Coord(i, (i * 7) % 256)).collect();
+
+    let mut group = c.benchmark_group("neighbors");
+    group.throughput(Throughput::Elements(coords.len() as u64));
+    group.bench_function("neighbor_coords", |b| {
+        b.iter(|| {
+            coords.iter().for_each(|&coord| {
+                black_box(environment.neighbor_coords(coord));
+            })
+        })
+    });
+    // Boxes a copy of every neighbor agent
+    group.bench_function("get_empty_neighbors", |b| {
+        b.iter(|| {
+            coords
+                .iter()
+                .map(|&coord| environment.get_empty_neighbors(coord).len())
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+fn gen_agents_grouped(c: &mut Criterion) {
+    let mut group = c.benchmark_group("gen_agents_grouped");
+    group.sample_size(10);
+    for &agents in [1000u32, 16_000, 128_000].iter() {
+        group.throughput(Throughput::Elements(agents as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(agents),
+            &agents,
+            |b, &agents| {
+                b.iter_batched_ref(
+                    || {
+                        rng::seed(SEED);
+                        Sma::new(512, 512, 4, 10, 4, true)
+                    },
+                    |sma| sma.gen_agents_grouped(agents - agents / 10, agents / 10),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+// What the frontends and exports read from the board after each tick
+fn render_data(c: &mut Criterion) {
+    let environment = board(512, 0.5).environment;
+    let mut colors = vec![[0; 4]; environment.size()];
+
+    let mut group = c.benchmark_group("render_data");
+    group.throughput(Throughput::Elements(environment.size() as u64));
+    group.bench_function("fill_colors", |b| {
+        b.iter(|| environment.fill_colors(&mut colors))
+    });
+    group.bench_function("colors", |b| b.iter(|| environment.colors()));
+    group.bench_function("map", |b| b.iter(|| Map::from_environment(&environment)));
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    update_all,
+    neighbors,
+    gen_agents_grouped,
+    render_data
+);
+criterion_main!(benches);
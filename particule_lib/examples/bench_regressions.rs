@@ -0,0 +1,102 @@
+// Compare the last `cargo bench` run to a saved baseline and fail when a benchmark got slower:
+//
+//     cargo bench -p particule_lib -- --save-baseline main   (before the change)
+//     cargo bench -p particule_lib -- --baseline main        (after it)
+//     cargo run -p particule_lib --example bench_regressions -- main 5
+//
+// Arguments are the baseline name and the slowdown in percent allowed, `main` and 5 by default.
+// Criterion keeps each benchmark in `target/criterion/<group>/<id>`, `new` holds the last run.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+// Mean time of a run in nanoseconds
+fn mean(path: &Path) -> Result<f64, String> {
+    let mut estimates = config::Config::default();
+    estimates
+        .merge(config::File::from(path))
+        .and_then(|estimates| estimates.get_float("mean.point_estimate"))
+        .map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+// Benchmark directories holding both the last run and the baseline
+fn benchmarks(dir: &Path, baseline: &str, found: &mut Vec<PathBuf>) {
+    if dir.join("new").is_dir() && dir.join(baseline).is_dir() {
+        found.push(dir.to_path_buf());
+    }
+    if let Ok(entries) = fs::read_dir(dir) {
+        let mut children: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_dir())
+            .collect();
+        children.sort();
+        children
+            .iter()
+            .for_each(|child| benchmarks(child, baseline, found));
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let baseline = args.first().map(String::as_str).unwrap_or("main");
+    let allowed = match args.get(1).map(|percent| percent.parse::<f64>()) {
+        None => 5.0,
+        Some(Ok(percent)) => percent,
+        Some(Err(err)) => {
+            eprintln!("invalid slowdown: {}", err);
+            process::exit(2);
+        }
+    };
+
+    let root = env::var("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| Path::new(env!("CARGO_MANIFEST_DIR")).join("../target"))
+        .join("criterion");
+    let mut found = vec![];
+    benchmarks(&root, baseline, &mut found);
+    if found.is_empty() {
+        eprintln!(
+            "no benchmark with a `{}` baseline in {}",
+            baseline,
+            root.display()
+        );
+        process::exit(2);
+    }
+
+    let mut regressions = 0;
+    for dir in found {
+        let name = dir.strip_prefix(&root).unwrap_or(&dir).display();
+        let (before, after) = match (
+            mean(&dir.join(baseline).join("estimates.json")),
+            mean(&dir.join("new").join("estimates.json")),
+        ) {
+            (Ok(before), Ok(after)) => (before, after),
+            (Err(err), _) | (_, Err(err)) => {
+                eprintln!("{}", err);
+                process::exit(2);
+            }
+        };
+        let change = (after / before - 1.0) * 100.0;
+        let regressed = change > allowed;
+        if regressed {
+            regressions += 1;
+        }
+        println!(
+            "{:<40} {:>12.0} ns -> {:>12.0} ns {:>+7.1} %{}",
+            name,
+            before,
+            after,
+            change,
+            if regressed { "  slower" } else { "" }
+        );
+    }
+
+    if regressions > 0 {
+        eprintln!(
+            "{} benchmarks more than {} % slower than `{}`",
+            regressions, allowed, baseline
+        );
+        process::exit(1);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::trace::Populations;
+use std::cmp::Ordering;
+use std::fmt;
+
+// Lowest autocorrelation of a peak that makes the populations oscillate rather than wander
+const MIN_CORRELATION: f64 = 0.2;
+// Full periods the steady state must hold to measure them
+const MIN_PERIODS: f64 = 2.0;
+
+// Regular swing of the populations of one species
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Oscillation {
+    // Ticks between two peaks
+    pub period: f64,
+    // Half the swing from trough to peak over a period, in agents
+    pub amplitude: f64,
+    // Autocorrelation at one period, 1 for perfectly regular cycles
+    pub regularity: f64,
+}
+
+// What became of one species
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Species {
+    // First record from which no agent of the species is left
+    pub extinction: Option<u64>,
+    // Mean population over the steady state
+    pub mean: f64,
+    pub oscillation: Option<Oscillation>,
+}
+
+// Summary of a run from its populations. The first half of the records is left out
+// as the transient, the steady state is the second half
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Analysis {
+    pub fish: Species,
+    pub sharks: Species,
+    // First record of the steady state
+    pub steady_from: u64,
+    // Ticks from a fish peak to the shark peak that follows it, when both oscillate
+    pub lag: Option<f64>,
+}
+
+pub fn analyze(populations: &Populations) -> Analysis {
+    let steady_from = populations.len() / 2;
+    let fish = species(&populations.fish, steady_from);
+    let sharks = species(&populations.sharks, steady_from);
+
+    let lag = match (fish.oscillation, sharks.oscillation) {
+        (Some(fish_cycle), Some(shark_cycle)) => {
+            let period = (fish_cycle.period + shark_cycle.period) / 2.0;
+            lag(
+                &to_f64(&populations.fish[steady_from..]),
+                &to_f64(&populations.sharks[steady_from..]),
+                period,
+            )
+        }
+        _ => None,
+    };
+    Analysis {
+        fish,
+        sharks,
+        steady_from: steady_from as u64,
+        lag,
+    }
+}
+
+fn species(counts: &[u64], steady_from: usize) -> Species {
+    let extinction = extinction(counts);
+    let steady = to_f64(&counts[steady_from..]);
+    Species {
+        extinction,
+        mean: mean(&steady),
+        oscillation: if extinction.is_some() {
+            None
+        } else {
+            oscillation(&steady)
+        },
+    }
+}
+
+// First record of the run of zeros that ends the records
+fn extinction(counts: &[u64]) -> Option<u64> {
+    match counts.last() {
+        Some(0) => {
+            let alive = counts.iter().rposition(|&count| count > 0);
+            Some(alive.map_or(0, |last| last as u64 + 1))
+        }
+        _ => None,
+    }
+}
+
+// The period is the lag of the first autocorrelation peak, between the second and third
+// times it changes sign, refined between ticks by the parabola through the peak and its
+// neighbours
+fn oscillation(values: &[f64]) -> Option<Oscillation> {
+    let correlations = autocorrelations(values, values.len() / 2)?;
+    let below = correlations
+        .iter()
+        .position(|&correlation| correlation < 0.0)?;
+    let above = below
+        + correlations[below..]
+            .iter()
+            .position(|&correlation| correlation >= 0.0)?;
+    let end = correlations[above..]
+        .iter()
+        .position(|&correlation| correlation < 0.0)
+        .map_or(correlations.len(), |end| above + end);
+    let (peak, &regularity) = correlations[..end]
+        .iter()
+        .enumerate()
+        .skip(above)
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
+    if regularity < MIN_CORRELATION || peak + 1 >= correlations.len() {
+        return None;
+    }
+
+    let period = peak as f64 + vertex(&correlations[peak - 1..=peak + 1]);
+    if (values.len() as f64) < MIN_PERIODS * period {
+        return None;
+    }
+    Some(Oscillation {
+        period,
+        amplitude: amplitude(values, period.round() as usize),
+        regularity,
+    })
+}
+
+// Ticks the sharks follow the fish by: the lag within a period where the fish
+// populations best match the shark populations that come after them. A whole period
+// is the same as no lag, it is left out
+fn lag(fish: &[f64], sharks: &[f64], period: f64) -> Option<f64> {
+    let lags = (period.round() as usize)
+        .saturating_sub(1)
+        .min(fish.len() - 1);
+    let correlations: Vec<f64> = (0..=lags)
+        .map(|lag| correlation(&fish[..fish.len() - lag], &sharks[lag..]))
+        .collect();
+    let (best, _) = correlations
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
+    // Refined with the neighbours of the best lag, the first and last ones have one only
+    if best == 0 || best == lags {
+        return Some(best as f64);
+    }
+    Some(best as f64 + vertex(&correlations[best - 1..=best + 1]))
+}
+
+// Correlations of `values` with themselves shifted by 0 to `lags` ticks, none when
+// they never change
+fn autocorrelations(values: &[f64], lags: usize) -> Option<Vec<f64>> {
+    let center = mean(values);
+    let deviations: Vec<f64> = values.iter().map(|value| value - center).collect();
+    let squares: f64 = deviations
+        .iter()
+        .map(|deviation| deviation * deviation)
+        .sum();
+    let variance = squares / values.len() as f64;
+    if variance <= 0.0 {
+        return None;
+    }
+    // Each lag averages its own products, a perfect cycle keeps a correlation of 1
+    let correlations = (0..=lags)
+        .map(|lag| {
+            let products = deviations.iter().zip(&deviations[lag..]);
+            let sum: f64 = products.map(|(a, b)| a * b).sum();
+            sum / (values.len() - lag) as f64 / variance
+        })
+        .collect();
+    Some(correlations)
+}
+
+// Pearson correlation of two series of the same length, 0 when one never changes
+fn correlation(a: &[f64], b: &[f64]) -> f64 {
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let mut covariance = 0.0;
+    let mut variances = (0.0, 0.0);
+    for (a, b) in a.iter().zip(b) {
+        covariance += (a - mean_a) * (b - mean_b);
+        variances.0 += (a - mean_a).powi(2);
+        variances.1 += (b - mean_b).powi(2);
+    }
+    if variances.0 <= 0.0 || variances.1 <= 0.0 {
+        return 0.0;
+    }
+    covariance / (variances.0 * variances.1).sqrt()
+}
+
+// Mean half swing over the full periods of `values`
+fn amplitude(values: &[f64], period: usize) -> f64 {
+    let swings: Vec<f64> = values
+        .chunks_exact(period.max(1))
+        .map(|window| {
+            let max = window.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let min = window.iter().copied().fold(f64::INFINITY, f64::min);
+            (max - min) / 2.0
+        })
+        .collect();
+    mean(&swings)
+}
+
+// Offset from the middle of three evenly spaced values to the top of the parabola
+// through them, between -0.5 and 0.5 around a peak
+fn vertex(values: &[f64]) -> f64 {
+    let (before, at, after) = (values[0], values[1], values[2]);
+    let curvature = before - 2.0 * at + after;
+    if curvature >= 0.0 {
+        return 0.0;
+    }
+    (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn to_f64(counts: &[u64]) -> Vec<f64> {
+    counts.iter().map(|&count| count as f64).collect()
+}
+
+// Extinction tick, or the steady state mean with the period and amplitude of the cycles
+impl fmt::Display for Species {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(tick) = self.extinction {
+            return write!(f, "extinct at tick {}", tick);
+        }
+        write!(f, "mean {:.1}", self.mean)?;
+        match self.oscillation {
+            Some(oscillation) => write!(
+                f,
+                ", period {:.1}, amplitude {:.1}",
+                oscillation.period, oscillation.amplitude
+            ),
+            None => write!(f, ", no oscillation"),
+        }
+    }
+}
+
+// One line per species then the lag of the sharks
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "fish: {}", self.fish)?;
+        write!(f, "sharks: {}", self.sharks)?;
+        if let Some(lag) = self.lag {
+            write!(f, "\nlag: sharks peak {:.1} ticks after fish", lag)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,787 @@
+use crate::evolution;
+use crate::AgentKind;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Simulation settings shared by every frontend, usually read from `config.json`
+// or from one of the scenarios of a scenario file
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Config {
+    #[serde(default)]
+    pub model: Model,
+    // Board size and populations are read from the map when there is one
+    #[serde(default)]
+    pub x: f32,
+    #[serde(default)]
+    pub y: f32,
+    #[serde(default = "default_cell_size")]
+    pub cell_size: f32,
+    // Topology: toric world when true, walls on the edges otherwise
+    pub borderless: bool,
+    #[serde(default)]
+    pub grid: bool,
+    // How the initial population is laid out
+    #[serde(default)]
+    pub placement: Placement,
+    // Per species placement, each species falls back to `placement`
+    #[serde(default)]
+    pub fish_placement: Option<Placement>,
+    #[serde(default)]
+    pub shark_placement: Option<Placement>,
+    #[serde(default)]
+    pub fish_number: u32,
+    #[serde(default)]
+    pub shark_number: u32,
+    // Text or png map of the initial world, see `map::Map`
+    #[serde(default)]
+    pub map: Option<String>,
+    // Rectangles of cells no agent can enter, added to the walls of the map
+    #[serde(default)]
+    pub walls: Vec<Wall>,
+    // Areas where the breed and starve times differ, see `habitat::Habitat`
+    #[serde(default)]
+    pub habitat: Vec<HabitatLayer>,
+    pub fish_breed_time: i32,
+    pub shark_breed_time: i32,
+    pub shark_starve_time: i32,
+    // How a shark picks the fish it eats among the ones around it
+    #[serde(default)]
+    pub prey_policy: PreyPolicy,
+    // How agents breed, `fish_breeding` and `shark_breeding` replace it for one species
+    #[serde(default)]
+    pub breeding: Breeding,
+    #[serde(default)]
+    pub fish_breeding: Option<Breeding>,
+    #[serde(default)]
+    pub shark_breeding: Option<Breeding>,
+    // Ticks an agent lives before dying of old age, forever when missing
+    #[serde(default)]
+    pub fish_lifespan: Option<u32>,
+    #[serde(default)]
+    pub shark_lifespan: Option<u32>,
+    // Traits of the agents placed at the start, young inherit the traits of their parent
+    #[serde(default)]
+    pub fish_traits: Traits,
+    #[serde(default)]
+    pub shark_traits: Traits,
+    // Young traits mutate when set, they are copied unchanged otherwise
+    #[serde(default)]
+    pub evolution: Option<Evolution>,
+    // Rgb colors, channels in [0, 1]
+    #[serde(default = "default_fish_color")]
+    pub fish_color: [f32; 3],
+    #[serde(default = "default_shark_color")]
+    pub shark_color: [f32; 3],
+    // Random seed, the same seed and config always give the same run
+    #[serde(default)]
+    pub seed: Option<u64>,
+    // Stop after this many ticks
+    #[serde(default)]
+    pub ticks: Option<u64>,
+    // Start paused in interactive frontends
+    #[serde(default)]
+    pub paused: bool,
+    #[serde(default)]
+    pub export: Option<ExportConfig>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Model {
+    #[default]
+    Wator,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PreyPolicy {
+    // Any fish around
+    #[default]
+    Random,
+    // Highest age first
+    Oldest,
+    // Fewest free cells around the fish first
+    Cornered,
+}
+
+// Both species breed the same way: an agent is ready once its breed count down reached 0,
+// it stays ready until its next move and leaves its young on the cell it left.
+// `{ when_blocked = "skip", litter = 2, maturity = 10, adult_age = 20 }`
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Breeding {
+    #[serde(default)]
+    pub when_blocked: WhenBlocked,
+    // Young born at once, the ones after the first take free cells around the parent
+    #[serde(default = "default_litter")]
+    pub litter: u32,
+    // Breed count down of the young, ticks before they can breed for the first time.
+    // The breed time when missing
+    #[serde(default)]
+    pub maturity: Option<i32>,
+    // Age under which agents are juveniles and don't breed, even once their count down
+    // reached 0
+    #[serde(default)]
+    pub adult_age: u32,
+}
+
+impl Breeding {
+    pub const DEFAULT: Breeding = Breeding {
+        when_blocked: WhenBlocked::Wait,
+        litter: 1,
+        maturity: None,
+        adult_age: 0,
+    };
+}
+
+impl Default for Breeding {
+    fn default() -> Breeding {
+        Breeding::DEFAULT
+    }
+}
+
+// What a ready agent with no free cell around it does, there is no room for its young
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WhenBlocked {
+    // Stay ready and breed as soon as it can move
+    #[default]
+    Wait,
+    // Give up this breeding, the count down starts again
+    Skip,
+    // Stay ready and, as soon as a cell around is free, breed without moving: the agent
+    // stays put and its young take the free cells
+    #[serde(rename = "in_place")]
+    InPlace,
+}
+
+// Heritable parameters of an agent, `{ breed = 1.2, starve = 0.8, vision = 3, speed = 2 }`
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Traits {
+    // Factors of the breed and starve times, on top of the habitat ones
+    #[serde(default = "default_factor")]
+    pub breed: f32,
+    #[serde(default = "default_factor")]
+    pub starve: f32,
+    // Distance in cells at which a fish sees sharks and flees them, and a shark with no fish
+    // around sees fish and heads to them. 0 is blind
+    #[serde(default)]
+    pub vision: u8,
+    // Cells moved per tick, each cell after the first costs a shark one tick of starvation
+    #[serde(default = "default_speed")]
+    pub speed: u8,
+}
+
+impl Traits {
+    // Agents without traits: their species times, no vision and one cell per tick
+    pub const DEFAULT: Traits = Traits {
+        breed: 1.0,
+        starve: 1.0,
+        vision: 0,
+        speed: 1,
+    };
+}
+
+impl Default for Traits {
+    fn default() -> Traits {
+        Traits::DEFAULT
+    }
+}
+
+// How the traits of young differ from their parent's, `{ rate = 0.1, size = 0.2 }`
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Evolution {
+    // Chance that each trait of a young mutates
+    #[serde(default = "default_mutation_rate")]
+    pub rate: f32,
+    // Largest relative change of the breed and starve factors, vision and speed change
+    // by one cell
+    #[serde(default = "default_mutation_size")]
+    pub size: f32,
+}
+
+// Either a name, `"random"`, or a table with the strategy parameters,
+// `{ "clusters": { "count": 4 } }`
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Placement {
+    // Agents spread uniformly over the board
+    Random,
+    // A single group grown around a random cell
+    #[default]
+    Grouped,
+    // `count` groups grown at the same time around random cells. `compactness` in [-1, 1]
+    // favors the cells most surrounded when positive and branches out when negative
+    Clusters {
+        count: u32,
+        #[serde(default)]
+        compactness: f32,
+    },
+    // Bands `width` cells wide separated by `gap` cells, horizontal unless `vertical`
+    Stripes {
+        width: u32,
+        gap: u32,
+        #[serde(default)]
+        vertical: bool,
+    },
+    // One half of the board, give each species its own side to keep them apart
+    Half {
+        side: Side,
+    },
+    // Denser where a smooth random noise is high, `scale` is the size of its blobs in cells
+    Noise {
+        scale: f32,
+    },
+    // Cells marked in a text map or a png image the size of the board
+    Map {
+        path: String,
+    },
+}
+
+// `width` x `height` cells from (x, y), the bottom left corner
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Wall {
+    pub x: i32,
+    pub y: i32,
+    #[serde(default = "default_wall_side")]
+    pub width: i32,
+    #[serde(default = "default_wall_side")]
+    pub height: i32,
+}
+
+// Multiplies the breed and starve times by its factors over its shape,
+// `{ shape = { zone = { x = 0, y = 0, width = 50, height = 100 } }, fish_breed = 0.5 }`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct HabitatLayer {
+    pub shape: Shape,
+    #[serde(default = "default_factor")]
+    pub fish_breed: f32,
+    #[serde(default = "default_factor")]
+    pub shark_breed: f32,
+    #[serde(default = "default_factor")]
+    pub shark_starve: f32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Shape {
+    Everywhere,
+    // `width` x `height` cells from (x, y), the bottom left corner
+    Zone {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    // From nothing on the opposite side to the full factors on `toward`
+    Gradient {
+        toward: Side,
+    },
+    // Strength of each cell read from a text or png map the size of the board
+    Map {
+        path: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+// Several named configs in a single file, `default` names the one used
+// when none is asked for
+#[derive(Debug, Deserialize)]
+struct ScenarioFile {
+    default: Option<String>,
+    scenarios: BTreeMap<String, Config>,
+}
+
+impl Config {
+    // Breeding rule of one species
+    pub fn breeding_of(&self, kind: AgentKind) -> Breeding {
+        let breeding = match kind {
+            AgentKind::Fish => self.fish_breeding,
+            AgentKind::Shark => self.shark_breeding,
+        };
+        breeding.unwrap_or(self.breeding)
+    }
+
+    // Read a json, toml or yaml file, the format is picked from the extension.
+    // The file either holds a single config or named `scenarios`, `scenario`
+    // picks one of them
+    pub fn from_file<P: AsRef<Path>>(
+        path: P,
+        scenario: Option<&str>,
+    ) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        fs::metadata(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+
+        let parse_error = |err| ConfigError::Parse(path.to_path_buf(), err);
+        let mut settings = config::Config::default();
+        settings
+            .merge(config::File::from(path))
+            .map_err(parse_error)?;
+
+        if settings.get_table("scenarios").is_err() {
+            return match scenario {
+                Some(name) => Err(ConfigError::UnknownScenario(name.into(), vec![])),
+                None => settings.try_into().map_err(parse_error),
+            };
+        }
+
+        let mut file: ScenarioFile = settings.try_into().map_err(parse_error)?;
+        let available = file.scenarios.keys().cloned().collect::<Vec<String>>();
+        // Keys are case insensitive in config files
+        let name = match scenario.or(file.default.as_deref()) {
+            Some(name) => name.to_lowercase(),
+            None if available.len() == 1 => available[0].clone(),
+            None => return Err(ConfigError::NoScenario(available)),
+        };
+
+        file.scenarios
+            .remove(&name)
+            .ok_or_else(|| ConfigError::UnknownScenario(name, available))
+    }
+
+    // Check every setting before the simulation starts, reporting all the problems at once
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut errors = vec![];
+
+        // The map gives the board size and the populations
+        if self.map.is_none() {
+            check_size(&mut errors, "x", self.x);
+            check_size(&mut errors, "y", self.y);
+            if self.x >= 1.0 && self.y >= 1.0 && (self.x as f64) * (self.y as f64) > i32::MAX as f64
+            {
+                errors.push(ValidationError::new(
+                    "x",
+                    "x * y cells do not fit in the board",
+                ));
+            }
+
+            let cells = (self.x.max(0.0) as u64) * (self.y.max(0.0) as u64);
+            let agents = self.fish_number as u64 + self.shark_number as u64;
+            if agents > cells {
+                errors.push(ValidationError::new(
+                    "fish_number",
+                    format!(
+                        "fish_number + shark_number ({}) is larger than the {} cells of the board",
+                        agents, cells
+                    ),
+                ));
+            }
+        }
+        if let Some(path) = &self.map {
+            check_path(&mut errors, "map", path);
+        }
+
+        self.walls.iter().for_each(|wall| {
+            if wall.width < 1 || wall.height < 1 {
+                errors.push(ValidationError::new(
+                    "walls",
+                    format!(
+                        "wall at ({}, {}): width and height must be at least 1",
+                        wall.x, wall.y
+                    ),
+                ));
+            }
+            let outside = wall.x < 0
+                || wall.y < 0
+                || (self.map.is_none()
+                    && (wall.x as f32 + wall.width as f32 > self.x
+                        || wall.y as f32 + wall.height as f32 > self.y));
+            if outside {
+                errors.push(ValidationError::new(
+                    "walls",
+                    format!(
+                        "wall at ({}, {}) of {}x{} cells is outside the board",
+                        wall.x, wall.y, wall.width, wall.height
+                    ),
+                ));
+            }
+        });
+
+        if self.cell_size.is_nan() || self.cell_size <= 0.0 {
+            errors.push(ValidationError::new("cell_size", "must be greater than 0"));
+        }
+
+        check_count_down(&mut errors, "fish_breed_time", self.fish_breed_time);
+        check_count_down(&mut errors, "shark_breed_time", self.shark_breed_time);
+        check_count_down(&mut errors, "shark_starve_time", self.shark_starve_time);
+
+        check_breeding(&mut errors, "breeding", &self.breeding);
+        if let Some(breeding) = &self.fish_breeding {
+            check_breeding(&mut errors, "fish_breeding", breeding);
+        }
+        if let Some(breeding) = &self.shark_breeding {
+            check_breeding(&mut errors, "shark_breeding", breeding);
+        }
+
+        check_lifespan(&mut errors, "fish_lifespan", self.fish_lifespan);
+        check_lifespan(&mut errors, "shark_lifespan", self.shark_lifespan);
+
+        check_traits(&mut errors, "fish_traits", &self.fish_traits);
+        check_traits(&mut errors, "shark_traits", &self.shark_traits);
+        if let Some(evolution) = &self.evolution {
+            if !(0.0..=1.0).contains(&evolution.rate) {
+                errors.push(ValidationError::new(
+                    "evolution",
+                    format!("rate must be between 0 and 1, got {}", evolution.rate),
+                ));
+            }
+            if !(0.0..1.0).contains(&evolution.size) {
+                errors.push(ValidationError::new(
+                    "evolution",
+                    format!(
+                        "size must be between 0 and 1 excluded, got {}",
+                        evolution.size
+                    ),
+                ));
+            }
+        }
+
+        check_color(&mut errors, "fish_color", self.fish_color);
+        check_color(&mut errors, "shark_color", self.shark_color);
+
+        self.habitat.iter().for_each(|layer| {
+            let factors = [layer.fish_breed, layer.shark_breed, layer.shark_starve];
+            if factors
+                .iter()
+                .any(|factor| !factor.is_finite() || *factor < 0.0)
+            {
+                errors.push(ValidationError::new(
+                    "habitat",
+                    format!("factors must not be negative, got {:?}", factors),
+                ));
+            }
+            match layer.shape {
+                Shape::Zone {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => {
+                    if width < 1 || height < 1 {
+                        errors.push(ValidationError::new(
+                            "habitat",
+                            "zone width and height must be at least 1",
+                        ));
+                    }
+                    let outside = x < 0
+                        || y < 0
+                        || (self.map.is_none()
+                            && (x as f32 + width as f32 > self.x
+                                || y as f32 + height as f32 > self.y));
+                    if outside {
+                        errors.push(ValidationError::new(
+                            "habitat",
+                            format!(
+                                "zone at ({}, {}) of {}x{} cells is outside the board",
+                                x, y, width, height
+                            ),
+                        ));
+                    }
+                }
+                Shape::Map { ref path } => check_path(&mut errors, "habitat", path),
+                _ => (),
+            }
+        });
+
+        check_placement(&mut errors, "placement", &self.placement);
+        if let Some(placement) = &self.fish_placement {
+            check_placement(&mut errors, "fish_placement", placement);
+        }
+        if let Some(placement) = &self.shark_placement {
+            check_placement(&mut errors, "shark_placement", placement);
+        }
+
+        if let Some(export) = &self.export {
+            if export.every == 0 {
+                errors.push(ValidationError::new("export.every", "must be at least 1"));
+            }
+            if export.scale == 0 {
+                errors.push(ValidationError::new("export.scale", "must be at least 1"));
+            }
+            let max_side = self.x.max(self.y) as u64 * export.scale as u64;
+            if export.format == ExportFormat::Gif && max_side > u16::MAX as u64 {
+                errors.push(ValidationError::new(
+                    "export.scale",
+                    "gif frames are limited to 65535 pixels per side",
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+}
+
+fn check_size(errors: &mut Vec<ValidationError>, field: &'static str, value: f32) {
+    if value.is_nan() || value < 1.0 {
+        errors.push(ValidationError::new(
+            field,
+            format!("must be at least 1, got {}", value),
+        ));
+    } else if value.fract() != 0.0 {
+        errors.push(ValidationError::new(
+            field,
+            format!("must be a whole number of cells, got {}", value),
+        ));
+    }
+}
+
+fn check_count_down(errors: &mut Vec<ValidationError>, field: &'static str, value: i32) {
+    if value < 0 {
+        errors.push(ValidationError::new(
+            field,
+            format!("must not be negative, got {}", value),
+        ));
+    }
+}
+
+// Maps are read once the config is valid, a missing file is reported with the other errors
+fn check_path(errors: &mut Vec<ValidationError>, field: &'static str, path: &str) {
+    if !Path::new(path).is_file() {
+        errors.push(ValidationError::new(field, format!("no file at {}", path)));
+    }
+}
+
+fn check_breeding(errors: &mut Vec<ValidationError>, field: &'static str, breeding: &Breeding) {
+    // The parent moves to one of its 8 neighbors and its young fill the cell it left
+    // and the 7 others
+    if !(1..=8).contains(&breeding.litter) {
+        errors.push(ValidationError::new(
+            field,
+            format!("litter must be between 1 and 8, got {}", breeding.litter),
+        ));
+    }
+    if let Some(maturity) = breeding.maturity {
+        if maturity < 0 {
+            errors.push(ValidationError::new(
+                field,
+                format!("maturity must not be negative, got {}", maturity),
+            ));
+        }
+    }
+}
+
+fn check_lifespan(errors: &mut Vec<ValidationError>, field: &'static str, lifespan: Option<u32>) {
+    if lifespan == Some(0) {
+        errors.push(ValidationError::new(field, "must be at least 1"));
+    }
+}
+
+fn check_traits(errors: &mut Vec<ValidationError>, field: &'static str, traits: &Traits) {
+    let factors = evolution::MIN_FACTOR..=evolution::MAX_FACTOR;
+    if !factors.contains(&traits.breed) || !factors.contains(&traits.starve) {
+        errors.push(ValidationError::new(
+            field,
+            format!(
+                "breed and starve must be between {} and {}, got {} and {}",
+                evolution::MIN_FACTOR,
+                evolution::MAX_FACTOR,
+                traits.breed,
+                traits.starve
+            ),
+        ));
+    }
+    if traits.vision > evolution::MAX_VISION {
+        errors.push(ValidationError::new(
+            field,
+            format!(
+                "vision must be at most {}, got {}",
+                evolution::MAX_VISION,
+                traits.vision
+            ),
+        ));
+    }
+    if !(1..=evolution::MAX_SPEED).contains(&traits.speed) {
+        errors.push(ValidationError::new(
+            field,
+            format!(
+                "speed must be between 1 and {}, got {}",
+                evolution::MAX_SPEED,
+                traits.speed
+            ),
+        ));
+    }
+}
+
+fn check_color(errors: &mut Vec<ValidationError>, field: &'static str, color: [f32; 3]) {
+    if color.iter().any(|channel| !(0.0..=1.0).contains(channel)) {
+        errors.push(ValidationError::new(
+            field,
+            format!("channels must be between 0 and 1, got {:?}", color),
+        ));
+    }
+}
+
+fn check_placement(errors: &mut Vec<ValidationError>, field: &'static str, placement: &Placement) {
+    match placement {
+        Placement::Clusters { count, compactness } => {
+            if *count == 0 {
+                errors.push(ValidationError::new(
+                    field,
+                    "clusters count must be at least 1",
+                ));
+            }
+            if !(-1.0..=1.0).contains(compactness) {
+                errors.push(ValidationError::new(
+                    field,
+                    "clusters compactness must be between -1 and 1",
+                ));
+            }
+        }
+        Placement::Stripes { width, .. } if *width == 0 => errors.push(ValidationError::new(
+            field,
+            "stripes width must be at least 1",
+        )),
+        Placement::Noise { scale } if scale.is_nan() || *scale <= 0.0 => errors.push(
+            ValidationError::new(field, "noise scale must be greater than 0"),
+        ),
+        Placement::Map { path } => check_path(errors, field, path),
+        _ => (),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ValidationError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl ValidationError {
+    fn new<S: Into<String>>(field: &'static str, message: S) -> ValidationError {
+        ValidationError {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, config::ConfigError),
+    // Requested scenario and the available ones
+    UnknownScenario(String, Vec<String>),
+    // The file has several scenarios and no default
+    NoScenario(Vec<String>),
+    Invalid(Vec<ValidationError>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => {
+                write!(f, "unable to read config file {}: {}", path.display(), err)
+            }
+            ConfigError::Parse(path, err) => {
+                write!(f, "invalid config file {}: {}", path.display(), err)
+            }
+            ConfigError::UnknownScenario(name, available) if available.is_empty() => {
+                write!(f, "no scenario named {}, the file has no scenarios", name)
+            }
+            ConfigError::UnknownScenario(name, available) => write!(
+                f,
+                "no scenario named {}, available scenarios: {}",
+                name,
+                available.join(", ")
+            ),
+            ConfigError::NoScenario(available) => write!(
+                f,
+                "pick a scenario with --scenario or set `default`, available scenarios: {}",
+                available.join(", ")
+            ),
+            ConfigError::Invalid(errors) => {
+                write!(f, "invalid config")?;
+                errors
+                    .iter()
+                    .try_for_each(|error| write!(f, "\n  - {}", error))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    // Numbered `frame_000042.png` files in the `path` directory
+    Png,
+    // A single animated gif written to `path`
+    Gif,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExportConfig {
+    pub format: ExportFormat,
+    pub path: String,
+    // Capture one tick out of `every`
+    #[serde(default = "default_every")]
+    pub every: u32,
+    // Side in pixels of a cell in the exported images
+    #[serde(default = "default_scale")]
+    pub scale: u32,
+}
+
+fn default_cell_size() -> f32 {
+    6.0
+}
+
+fn default_fish_color() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_shark_color() -> [f32; 3] {
+    [1.0, 0.0, 0.0]
+}
+
+fn default_factor() -> f32 {
+    1.0
+}
+
+fn default_speed() -> u8 {
+    1
+}
+
+fn default_mutation_rate() -> f32 {
+    0.1
+}
+
+fn default_mutation_size() -> f32 {
+    0.1
+}
+
+fn default_litter() -> u32 {
+    1
+}
+
+fn default_wall_side() -> i32 {
+    1
+}
+
+fn default_every() -> u32 {
+    1
+}
+
+fn default_scale() -> u32 {
+    1
+}
@@ -1,246 +1,765 @@
 use crate::borderless;
-use crate::trace::TickTrace;
+use crate::config::{PreyPolicy, Traits, WhenBlocked};
+use crate::evolution::{self, MAX_VISION};
+use crate::get_breed_time_of;
+use crate::get_breeding;
+use crate::get_fish_color;
+use crate::get_maturity_at;
+use crate::get_prey_policy;
+use crate::get_shark_color;
+use crate::get_starve_time_of;
+use crate::get_traits;
+#[cfg(feature = "invariants")]
+use crate::invariants::{self, Census};
+use crate::is_juvenile;
+use crate::is_past_lifespan;
+use crate::max_height;
+use crate::max_width;
+use crate::rng::with_rng;
+use crate::to_rgba;
+use crate::trace::{AgeStats, TickTrace, TraitStats};
+use crate::wator::fish::{self, Fish};
+use crate::wator::shark::{self, Prey, Shark};
 use crate::AgentImpl;
 use crate::AgentKind;
 use crate::Cell;
+use crate::CellKind;
 use crate::Coord;
 use crate::Decision;
-use std::convert::TryInto;
+use crate::Rgba;
+use crate::WALL_COLOR;
 
+use rand::Rng;
+
+// Breed count down of a ready agent that was blocked with `WhenBlocked::InPlace`, the
+// count down otherwise stops at 0
+pub const BLOCKED: i32 = -1;
+
+// The board is stored as one array per field, the cell `idx` is at `Coord::from_idx(idx)`.
+// A tick only reads and writes plain values, it never allocates.
+// Count downs and ages of empty cells and walls are 0, their traits the default ones.
+#[derive(Clone)]
 pub struct Environment {
-    pub board: Vec<Cell>,
     pub width: i32,
     pub height: i32,
+    pub kinds: Vec<CellKind>,
+    pub breed_count_downs: Vec<i32>,
+    pub starve_count_downs: Vec<i32>,
+    // Ticks lived by the agent of each cell
+    pub ages: Vec<u32>,
+    pub traits: Vec<Traits>,
+    // Ticks run on the board when the agent of each cell was last updated, an agent
+    // moving to a cell still to be updated is not updated again in the same tick
+    pub updated: Vec<u64>,
+    // Number of ticks run on this board
+    pub ticks: u64,
 }
 
 impl Environment {
     pub fn new(width: i32, height: i32) -> Environment {
-        let size = width * height;
-        let board = vec![];
+        let size = (width * height) as usize;
 
-        let mut env = Environment {
+        Environment {
             width,
             height,
-            board,
-        };
-
-        let mut board = vec![];
-
-        for idx in 0..size {
-            board.push(Cell::Empty(Coord::from_idx(idx)))
+            kinds: vec![CellKind::Empty; size],
+            breed_count_downs: vec![0; size],
+            starve_count_downs: vec![0; size],
+            ages: vec![0; size],
+            traits: vec![Traits::DEFAULT; size],
+            updated: vec![0; size],
+            ticks: 0,
         }
+    }
 
-        env.board = board;
-
-        env
+    // Number of cells
+    pub fn size(&self) -> usize {
+        self.kinds.len()
     }
 
-    pub fn update_all(&mut self) {
-        let size = self.board.len();
+    pub fn update_all(&mut self) -> TickTrace {
+        #[cfg(feature = "invariants")]
+        let census = Census::of(self);
 
         let mut trace = TickTrace::new();
-        for idx in 0..size {
-            let decision = if let Cell::Filled(agent) = &self.board[idx] {
-                match agent.get_kind() {
-                    AgentKind::Fish => {
-                        let neighbors = &self.get_empty_neighbors(agent.coordinate());
-                        Some((agent.decide(neighbors), AgentKind::Fish))
-                    }
-                    AgentKind::Shark => {
-                        let neighbors = &self.get_shark_neighbors(agent.coordinate());
-                        Some((agent.decide(neighbors), AgentKind::Shark))
-                    }
-                }
-            } else {
-                None
-            };
+        let tick = self.ticks + 1;
+        for idx in 0..self.size() {
+            update_cell(self, idx, tick, &mut trace);
+        }
 
-            if let Some((decision, agent_kind)) = decision {
-                match decision {
-                    Decision::Stall(position) => self.update_agent(position),
-                    Decision::Move(from, to) => {
-                        self.update_agent(from);
-                        &self.move_agent(from, to);
-                    }
-                    Decision::MoveAndBreed(from, to) => {
-                        self.update_agent(from);
-                        &self.breed_and_move_agent(from, to);
-
-                        trace.birth(agent_kind);
-                    }
-                    Decision::EatAndMove(from, to) => {
-                        self.update_agent_and_reset_starve(from);
-                        self.remove_agent(to);
-                        self.move_agent(from, to);
-                        trace.death(AgentKind::Fish);
-                    }
-                    Decision::EatAndBreed(from, to) => {
-                        self.update_agent_and_reset_starve(from);
-                        self.remove_agent(to);
-                        self.breed_and_move_agent(from, to);
-                        trace.death(AgentKind::Fish);
-                        trace.birth(AgentKind::Shark);
-                    }
-                    Decision::Starve(position) => {
-                        self.remove_agent(position);
-                        trace.death(AgentKind::Shark);
-                    }
-                };
-            }
+        #[cfg(feature = "invariants")]
+        invariants::check_tick(self, tick, &census, &trace);
+        self.ticks += 1;
+        trace
+    }
+
+    // Put a new born agent on `coord` if the cell is empty
+    pub fn spawn(&mut self, kind: AgentKind, coord: Coord) {
+        let idx = coord.as_idx();
+        if self.kinds[idx] == CellKind::Empty {
+            let traits = get_traits(kind);
+            let count_downs = birth_count_downs(kind, coord, &traits);
+            self.set(idx, kind.into(), count_downs, 0, traits);
         }
-        println!("{}", trace);
     }
 
-    pub fn update_agent(&mut self, coord: Coord) {
-        let agent: &mut AgentImpl = self.get_mut_agent(coord).unwrap();
-        agent.update();
+    pub fn add_agent(&mut self, agent: AgentImpl) {
+        let idx = agent.coordinate().as_idx();
+        if self.kinds[idx] == CellKind::Empty {
+            let count_downs = (agent.get_breed_count_down(), agent.get_starve_count_down());
+            let (age, traits) = (agent.get_age(), agent.get_traits());
+            self.set(idx, agent.get_kind().into(), count_downs, age, traits);
+        }
     }
 
-    pub fn update_agent_and_reset_starve(&mut self, coord: Coord) {
-        let agent: &mut AgentImpl = self.get_mut_agent(coord).unwrap();
-        agent.update();
-        agent.reset_starve_count_down();
+    pub fn add_wall(&mut self, coord: Coord) {
+        let idx = coord.as_idx();
+        if self.kinds[idx] == CellKind::Empty {
+            self.set(idx, CellKind::Wall, (0, 0), 0, Traits::DEFAULT);
+        }
     }
 
-    pub fn add_agent(&mut self, agent: AgentImpl) {
-        let coord = &agent.coordinate();
+    pub fn update_agent(&mut self, coord: Coord) {
         let idx = coord.as_idx();
-        if self.board[idx].is_empty() {
-            self.board[idx] = Cell::Filled(agent)
+        match self.kinds[idx] {
+            CellKind::Fish => {
+                self.breed_count_downs[idx] = (self.breed_count_downs[idx] - 1).max(0);
+                self.ages[idx] = self.ages[idx].saturating_add(1);
+            }
+            CellKind::Shark => {
+                self.breed_count_downs[idx] = (self.breed_count_downs[idx] - 1).max(0);
+                self.starve_count_downs[idx] -= 1;
+                self.ages[idx] = self.ages[idx].saturating_add(1);
+            }
+            _ => (),
         }
     }
 
+    pub fn update_agent_and_reset_starve(&mut self, coord: Coord) {
+        self.update_agent(coord);
+        let idx = coord.as_idx();
+        self.starve_count_downs[idx] = get_starve_time_of(coord, &self.traits[idx]);
+    }
+
     pub fn remove_agent(&mut self, coord: Coord) {
+        self.clear(coord.as_idx());
+    }
+
+    // Agents of kind `kind` waiting to breed in place become ready like any other
+    pub(crate) fn unblock(&mut self, kind: AgentKind) {
+        let kind = CellKind::from(kind);
+        self.kinds
+            .iter()
+            .zip(self.breed_count_downs.iter_mut())
+            .filter(|(&cell, &mut count_down)| cell == kind && count_down == BLOCKED)
+            .for_each(|(_, count_down)| *count_down = 0);
+    }
+
+    // Copy of the cell on `coord`, agents are boxed on every call
+    pub fn cell(&self, coord: Coord) -> Cell {
         let idx = coord.as_idx();
-        self.board[idx] = Cell::Empty(coord);
+        let breed_count_down = self.breed_count_downs[idx];
+        let age = self.ages[idx];
+        let traits = self.traits[idx];
+        match self.kinds[idx] {
+            CellKind::Empty => Cell::Empty(coord),
+            CellKind::Wall => Cell::Wall(coord),
+            CellKind::Fish => Cell::Filled(Box::new(Fish {
+                coordinate: coord,
+                breed_count_down,
+                age,
+                traits,
+            })),
+            CellKind::Shark => Cell::Filled(Box::new(Shark {
+                coordinate: coord,
+                breed_count_down,
+                starve_count_down: self.starve_count_downs[idx],
+                age,
+                traits,
+            })),
+        }
     }
 
     pub fn get_empty_neighbors(&self, coord: Coord) -> Vec<Cell> {
-        self.get_neighbor(coord)
-            .iter()
-            .cloned()
-            .filter(|cell| cell.is_empty())
-            .collect()
+        self.neighbors(coord, |kind| kind == CellKind::Empty)
     }
 
     pub fn get_shark_neighbors(&self, coord: Coord) -> Vec<Cell> {
-        self.get_neighbor(coord)
+        self.neighbors(coord, |kind| {
+            kind == CellKind::Empty || kind == CellKind::Fish
+        })
+    }
+
+    fn neighbors<F: Fn(CellKind) -> bool>(&self, coord: Coord, keep: F) -> Vec<Cell> {
+        neighbor_coords(coord)
             .iter()
-            .cloned()
-            .filter(|cell| cell.is_fish() || cell.is_empty())
+            .filter(|neighbor| keep(self.kinds[neighbor.as_idx()]))
+            .map(|&neighbor| self.cell(neighbor))
             .collect()
     }
 
-    fn get_neighbor(&self, coord: Coord) -> Vec<Cell> {
-        let north;
-        let south;
-        let east;
-        let west;
-        let north_east;
-        let north_west;
-        let south_east;
-        let south_west;
-
-        if !borderless() {
-            north = coord + Coord(0, 1);
-            south = coord + Coord(0, -1);
-            east = coord + Coord(-1, 0);
-            west = coord + Coord(1, 0);
-            north_east = coord + Coord(-1, 1);
-            north_west = coord + Coord(1, 1);
-            south_east = coord + Coord(-1, -1);
-            south_west = coord + Coord(1, -1);
-        } else {
-            // It is not a multiplication !
-            // Mul operator overloading is used to get toric position
-            north = coord * Coord(0, 1);
-            south = coord * Coord(0, -1);
-            east = coord * Coord(-1, 0);
-            west = coord * Coord(1, 0);
-            north_east = coord * Coord(-1, 1);
-            north_west = coord * Coord(1, 1);
-            south_east = coord * Coord(-1, -1);
-            south_west = coord * Coord(1, -1);
-        }
-
-        vec![
-            self.get_cell(north),
-            self.get_cell(south),
-            self.get_cell(east),
-            self.get_cell(west),
-            self.get_cell(north_west),
-            self.get_cell(north_east),
-            self.get_cell(south_east),
-            self.get_cell(south_west),
-        ]
-    }
-
-    fn move_agent(&mut self, from: Coord, to: Coord) {
-        let agent: &mut Result<AgentImpl, String> = &mut self.get_cell(from).try_into();
-        if let Ok(agent) = agent {
-            self.set_empty_cell(agent.coordinate());
-            agent.set_coordinate(to);
-            self.set_agent_cell(&agent)
-        }
-    }
-
-    fn breed_and_move_agent(&mut self, from: Coord, to: Coord) {
-        let agent: &mut Result<AgentImpl, String> = &mut self.get_cell(from).try_into();
-
-        if let Ok(agent) = agent {
-            self.set_agent_cell(&agent.breed());
-            agent.set_coordinate(to);
-            self.set_agent_cell(&agent);
-        }
+    // The 8 surrounding positions, on a closed board a neighbor past the edge is `coord` itself
+    pub fn neighbor_coords(&self, coord: Coord) -> [Coord; 8] {
+        neighbor_coords(coord)
     }
 
     pub fn is_shark_at(&self, coord: Coord) -> bool {
-        let cell = self.get_cell(coord);
+        self.kinds[coord.as_idx()] == CellKind::Shark
+    }
+
+    pub fn count(&self, kind: AgentKind) -> usize {
+        let kind = CellKind::from(kind);
+        self.kinds.iter().filter(|&&cell| cell == kind).count()
+    }
+
+    // Age distribution of the agents of kind `kind`
+    pub fn age_stats(&self, kind: AgentKind) -> AgeStats {
+        let cell_kind = CellKind::from(kind);
+        let ages: Vec<u32> = self
+            .kinds
+            .iter()
+            .zip(self.ages.iter())
+            .filter(|(&cell, _)| cell == cell_kind)
+            .map(|(_, &age)| age)
+            .collect();
+        AgeStats::of(kind, ages)
+    }
+
+    // Trait distribution of the agents of kind `kind`
+    pub fn trait_stats(&self, kind: AgentKind) -> TraitStats {
+        let cell_kind = CellKind::from(kind);
+        TraitStats::of(
+            self.kinds
+                .iter()
+                .zip(self.traits.iter())
+                .filter(|(&cell, _)| cell == cell_kind)
+                .map(|(_, &traits)| traits),
+        )
+    }
 
-        if let Cell::Filled(agent) = cell {
-            if agent.get_kind() == AgentKind::Shark {
-                return true;
+    // One color per cell, row by row starting at the origin, empty cells are transparent
+    // and walls grey
+    pub fn colors(&self) -> Vec<Rgba> {
+        let mut colors = vec![[0; 4]; self.size()];
+        self.fill_colors(&mut colors);
+        colors
+    }
+
+    // Same as `colors` without allocating, `colors` must hold one entry per cell
+    pub fn fill_colors(&self, colors: &mut [Rgba]) {
+        let fish = to_rgba(get_fish_color());
+        let shark = to_rgba(get_shark_color());
+        self.kinds
+            .iter()
+            .zip(colors.iter_mut())
+            .for_each(|(kind, color)| {
+                *color = match kind {
+                    CellKind::Fish => fish,
+                    CellKind::Shark => shark,
+                    CellKind::Empty => [0; 4],
+                    CellKind::Wall => WALL_COLOR,
+                }
+            });
+    }
+
+    // Empty cells around every agent, once per agent next to them. Kept for callers of
+    // the boxed board, it builds a `Cell` per neighbor
+    #[deprecated(note = "read `kinds` and `neighbor_coords` instead")]
+    pub fn get_adjacent(&self) -> Vec<Coord> {
+        (0..self.size())
+            .filter(|&idx| self.kinds[idx].agent().is_some())
+            .flat_map(|idx| self.get_empty_neighbors(Coord::from_idx(idx as i32)))
+            .map(|cell| cell.to_coord_unchecked())
+            .collect()
+    }
+}
+
+// Cells a tick reads and writes: the whole board, or the tiles of it updated
+// at the same time when ticks run in parallel
+pub(crate) trait Board {
+    fn kind(&self, idx: usize) -> CellKind;
+    // Breed and starve count downs
+    fn count_downs(&self, idx: usize) -> (i32, i32);
+    fn age(&self, idx: usize) -> u32;
+    fn traits(&self, idx: usize) -> Traits;
+    // Ticks run when the agent on `idx` was last updated
+    fn updated(&self, idx: usize) -> u64;
+    fn stamp(&mut self, idx: usize, tick: u64);
+    fn set(
+        &mut self,
+        idx: usize,
+        kind: CellKind,
+        count_downs: (i32, i32),
+        age: u32,
+        traits: Traits,
+    );
+
+    fn clear(&mut self, idx: usize) {
+        self.set(idx, CellKind::Empty, (0, 0), 0, Traits::DEFAULT);
+    }
+}
+
+impl Board for Environment {
+    fn kind(&self, idx: usize) -> CellKind {
+        self.kinds[idx]
+    }
+
+    fn count_downs(&self, idx: usize) -> (i32, i32) {
+        (self.breed_count_downs[idx], self.starve_count_downs[idx])
+    }
+
+    fn age(&self, idx: usize) -> u32 {
+        self.ages[idx]
+    }
+
+    fn traits(&self, idx: usize) -> Traits {
+        self.traits[idx]
+    }
+
+    fn updated(&self, idx: usize) -> u64 {
+        self.updated[idx]
+    }
+
+    fn stamp(&mut self, idx: usize, tick: u64) {
+        self.updated[idx] = tick;
+    }
+
+    fn set(
+        &mut self,
+        idx: usize,
+        kind: CellKind,
+        count_downs: (i32, i32),
+        age: u32,
+        traits: Traits,
+    ) {
+        self.kinds[idx] = kind;
+        self.breed_count_downs[idx] = count_downs.0;
+        self.starve_count_downs[idx] = count_downs.1;
+        self.ages[idx] = age;
+        self.traits[idx] = traits;
+    }
+}
+
+// Let the agent on `idx` decide and apply its decision, unless it was already updated
+// during `tick`. It only reads and writes the cells within its vision and speed of `idx`.
+pub(crate) fn update_cell<B: Board + ?Sized>(
+    board: &mut B,
+    idx: usize,
+    tick: u64,
+    trace: &mut TickTrace,
+) {
+    let agent_kind = match board.kind(idx).agent() {
+        Some(agent_kind) if board.updated(idx) != tick => agent_kind,
+        _ => return,
+    };
+    let from = Coord::from_idx(idx as i32);
+    let (breed_count_down, starve_count_down) = board.count_downs(idx);
+    let age = board.age(idx);
+    let traits = board.traits(idx);
+    let neighbors = neighbor_coords(from);
+
+    let mut free = [from; 8];
+    let mut free_count = 0;
+    // Written every time and kept or not, cell kinds are too random to branch on
+    for &neighbor in neighbors.iter() {
+        free[free_count] = neighbor;
+        free_count += (board.kind(neighbor.as_idx()) == CellKind::Empty) as usize;
+    }
+    let free = &free[..free_count];
+
+    // Juveniles decide as if their count down had not reached 0 yet
+    let ready_count_down = if is_juvenile(agent_kind, age) {
+        breed_count_down.max(1)
+    } else {
+        breed_count_down
+    };
+    let decision = match agent_kind {
+        _ if is_past_lifespan(agent_kind, age) => Decision::Die(from),
+        AgentKind::Fish => {
+            let (moves, count) = heading(board, agent_kind, from, traits.vision, free);
+            fish::decide(from, ready_count_down, &moves[..count])
+        }
+        AgentKind::Shark => {
+            let policy = get_prey_policy();
+            let mut prey = [Prey {
+                coord: from,
+                age: 0,
+                escapes: 0,
+            }; 8];
+            let mut prey_count = 0;
+            for &neighbor in neighbors.iter() {
+                if board.kind(neighbor.as_idx()) == CellKind::Fish {
+                    prey[prey_count] = Prey {
+                        coord: neighbor,
+                        age: board.age(neighbor.as_idx()),
+                        // Only looked around when the policy needs it
+                        escapes: match policy {
+                            PreyPolicy::Cornered => free_cells(board, neighbor),
+                            _ => 0,
+                        },
+                    };
+                    prey_count += 1;
+                }
+            }
+            // A shark with fish around eats, it only looks farther otherwise
+            let (moves, count) = if prey_count == 0 {
+                heading(board, agent_kind, from, traits.vision, free)
+            } else {
+                ([from; 8], 0)
+            };
+            shark::decide(
+                from,
+                ready_count_down,
+                starve_count_down,
+                &moves[..count],
+                &prey[..prey_count],
+                policy,
+            )
+        }
+    };
+
+    // Count downs once the agent lived this tick, the breed count down stops at 0 until
+    // the agent breeds and a shark that eats is fed again
+    let kind = CellKind::from(agent_kind);
+    let age = age.saturating_add(1);
+    let breeding = get_breeding(agent_kind);
+    let aged = match agent_kind {
+        AgentKind::Fish => ((breed_count_down - 1).max(0), starve_count_down),
+        AgentKind::Shark => ((breed_count_down - 1).max(0), starve_count_down - 1),
+    };
+    let fed = || (aged.0, get_starve_time_of(from, &traits));
+    let parent = Parent {
+        kind: agent_kind,
+        starve_count_down: aged.1,
+        age,
+        traits,
+        tick,
+    };
+    // An agent blocked while ready with `WhenBlocked::InPlace` stays put to breed once a
+    // cell around it is free
+    let in_place = breed_count_down == BLOCKED
+        && breeding.when_blocked == WhenBlocked::InPlace
+        && matches!(decision, Decision::MoveAndBreed(..));
+    #[cfg(feature = "invariants")]
+    let target = invariants::target(board, &decision);
+
+    // Decisions all start from `from`, a shark eating takes the place of the fish
+    match decision {
+        // Only agents with no room around them stall
+        Decision::Stall(_) => {
+            let ready = ready_count_down <= 0;
+            let count_downs = match breeding.when_blocked {
+                WhenBlocked::Skip if ready => {
+                    (get_breed_time_of(agent_kind, from, &traits), aged.1)
+                }
+                WhenBlocked::InPlace if ready => (BLOCKED, aged.1),
+                _ => aged,
+            };
+            board.set(idx, kind, count_downs, age, traits);
+        }
+        Decision::Move(_, to) => move_agent(board, from, to, kind, aged, age, traits),
+        Decision::MoveAndBreed(_, to) if in_place => {
+            let young = breed_agent(board, from, to, &parent, free);
+            (0..young).for_each(|_| trace.birth(agent_kind));
+        }
+        Decision::MoveAndBreed(_, to) => {
+            let young = breed_agent(board, to, from, &parent, free);
+            (0..young).for_each(|_| trace.birth(agent_kind));
+        }
+        Decision::EatAndMove(_, to) => {
+            move_agent(board, from, to, kind, fed(), age, traits);
+            trace.death(AgentKind::Fish);
+        }
+        Decision::EatAndBreed(_, to) => {
+            let parent = Parent {
+                starve_count_down: fed().1,
+                ..parent
+            };
+            let young = breed_agent(board, to, from, &parent, free);
+            trace.death(AgentKind::Fish);
+            (0..young).for_each(|_| trace.birth(AgentKind::Shark));
+        }
+        Decision::Starve(_) => {
+            board.clear(idx);
+            trace.death(AgentKind::Shark);
+        }
+        Decision::Die(_) => {
+            board.clear(idx);
+            trace.death(agent_kind);
+        }
+    };
+
+    #[cfg(feature = "invariants")]
+    invariants::check_decision(board, agent_kind, &decision, &neighbors, target, trace);
+
+    // Faster agents keep going to free cells, they only eat and breed on their first move.
+    // The young of an agent breeding in place stay where they are born
+    let mut at = match decision.get_destination() {
+        _ if in_place => from,
+        Some(&to) => to,
+        None if board.kind(idx) == kind => from,
+        None => return,
+    };
+    if at != from {
+        for _ in 1..traits.speed {
+            match step(board, agent_kind, at, trace) {
+                Some(next) => at = next,
+                None => break,
             }
         }
+    }
+    board.stamp(at.as_idx(), tick);
+}
+
+// One more move of the agent on `at` to a free cell around it, toward or away from what
+// it sees. Each one costs a shark a tick of starvation
+fn step<B: Board + ?Sized>(
+    board: &mut B,
+    agent_kind: AgentKind,
+    at: Coord,
+    #[cfg_attr(not(feature = "invariants"), allow(unused_variables))] trace: &mut TickTrace,
+) -> Option<Coord> {
+    let idx = at.as_idx();
+    let neighbors = neighbor_coords(at);
+    let mut free = [at; 8];
+    let mut free_count = 0;
+    for &neighbor in neighbors.iter() {
+        free[free_count] = neighbor;
+        free_count += (board.kind(neighbor.as_idx()) == CellKind::Empty) as usize;
+    }
 
-        return false;
+    let traits = board.traits(idx);
+    let (moves, count) = heading(board, agent_kind, at, traits.vision, &free[..free_count]);
+    if count == 0 {
+        return None;
     }
+    let to = moves[with_rng(|rng| rng.gen_range(0, count))];
+
+    let (breed_count_down, starve_count_down) = board.count_downs(idx);
+    let count_downs = match agent_kind {
+        AgentKind::Fish => (breed_count_down, starve_count_down),
+        AgentKind::Shark => (breed_count_down, starve_count_down - 1),
+    };
+    #[cfg(feature = "invariants")]
+    let target = invariants::target(board, &Decision::Move(at, to));
+    let kind = CellKind::from(agent_kind);
+    move_agent(board, at, to, kind, count_downs, board.age(idx), traits);
+
+    #[cfg(feature = "invariants")]
+    invariants::check_decision(
+        board,
+        agent_kind,
+        &Decision::Move(at, to),
+        &neighbors,
+        target,
+        trace,
+    );
+    Some(to)
+}
 
-    fn get_cell(&self, coord: Coord) -> Cell {
-        self.board[coord.as_idx()].clone()
+// Cells seen by an agent, up to `MAX_VISION` cells in every direction
+const SIGHT: usize = (2 * MAX_VISION as usize + 1) * (2 * MAX_VISION as usize + 1);
+
+// The `free` cells a shark picks from to get closer to the fish it sees, or a fish to get
+// away from the sharks it sees, all of them when it sees none. Sharks always see the fish
+// around them, a vision of 1 changes nothing for them
+fn heading<B: Board + ?Sized>(
+    board: &B,
+    agent_kind: AgentKind,
+    from: Coord,
+    vision: u8,
+    free: &[Coord],
+) -> ([Coord; 8], usize) {
+    let mut moves = [from; 8];
+    moves[..free.len()].copy_from_slice(free);
+    let (other, closer) = match agent_kind {
+        AgentKind::Fish => (CellKind::Shark, false),
+        AgentKind::Shark => (CellKind::Fish, true),
+    };
+    if free.len() < 2 || vision == 0 || (closer && vision == 1) {
+        return (moves, free.len());
     }
 
-    fn get_mut_agent(&mut self, coord: Coord) -> Option<&mut AgentImpl> {
-        if let Cell::Filled(agent) = &mut self.board[coord.as_idx()] {
-            Some(agent)
-        } else {
-            None
+    let mut seen = [from; SIGHT];
+    let mut seen_count = 0;
+    let range = vision.min(MAX_VISION) as i32;
+    for dy in -range..=range {
+        for dx in -range..=range {
+            if let Some(cell) = shift(from, dx, dy) {
+                if board.kind(cell.as_idx()) == other {
+                    seen[seen_count] = cell;
+                    seen_count += 1;
+                }
+            }
         }
     }
+    if seen_count == 0 {
+        return (moves, free.len());
+    }
 
-    fn set_agent_cell(&mut self, agent: &AgentImpl) {
-        self.board[agent.coordinate().as_idx()] = Cell::Filled(agent.clone());
+    // Distance of each free cell to the closest agent seen
+    let mut distances = [0; 8];
+    free.iter()
+        .zip(distances.iter_mut())
+        .for_each(|(&cell, distance)| {
+            *distance = seen[..seen_count]
+                .iter()
+                .map(|&other| distance_between(cell, other))
+                .min()
+                .unwrap_or(0);
+        });
+    let distances = &distances[..free.len()];
+    let best = if closer {
+        distances.iter().min()
+    } else {
+        distances.iter().max()
+    };
+    let best = *best.unwrap_or(&0);
+
+    let mut count = 0;
+    for (&cell, &distance) in free.iter().zip(distances.iter()) {
+        moves[count] = cell;
+        count += (distance == best) as usize;
     }
+    (moves, count)
+}
 
-    fn set_empty_cell(&mut self, coord: Coord) {
-        self.board[coord.as_idx()] = Cell::Empty(coord);
+// `coord` moved by (dx, dy), past the edges of a toric board or none on a closed one
+fn shift(coord: Coord, dx: i32, dy: i32) -> Option<Coord> {
+    let (x, y) = (coord.0 + dx, coord.1 + dy);
+    if borderless() {
+        return Some(Coord(x.rem_euclid(max_width()), y.rem_euclid(max_height())));
+    }
+    if x < 0 || y < 0 || x >= max_width() || y >= max_height() {
+        return None;
     }
+    Some(Coord(x, y))
+}
 
-    pub fn get_adjacent(&self) -> Vec<Coord> {
-        let cells: Vec<Vec<Cell>> = self
-            .board
-            .iter()
-            .filter(|cell| !cell.is_empty())
-            .map(|cell| self.get_empty_neighbors(cell.to_coord_unchecked()))
-            .collect();
+// Moves needed to go from `a` to `b`, diagonals included
+fn distance_between(a: Coord, b: Coord) -> i32 {
+    let mut dx = (a.0 - b.0).abs();
+    let mut dy = (a.1 - b.1).abs();
+    if borderless() {
+        dx = dx.min(max_width() - dx);
+        dy = dy.min(max_height() - dy);
+    }
+    dx.max(dy)
+}
 
-        let coords = cells
-            .iter()
-            .flatten()
-            .map(|cell| cell.try_into())
-            .map(|coord| coord.unwrap())
-            .collect::<Vec<Coord>>();
+fn free_cells<B: Board + ?Sized>(board: &B, coord: Coord) -> u8 {
+    neighbor_coords(coord)
+        .iter()
+        .filter(|neighbor| board.kind(neighbor.as_idx()) == CellKind::Empty)
+        .count() as u8
+}
+
+// Count downs of an agent with `traits` born on `coord`
+fn birth_count_downs(kind: AgentKind, coord: Coord, traits: &Traits) -> (i32, i32) {
+    match kind {
+        AgentKind::Fish => (get_maturity_at(kind, coord, traits), 0),
+        AgentKind::Shark => (
+            get_maturity_at(kind, coord, traits),
+            get_starve_time_of(coord, traits),
+        ),
+    }
+}
+
+fn neighbor_coords(coord: Coord) -> [Coord; 8] {
+    if borderless() {
+        // Toric board, the columns and rows past the edges are the ones on the other side
+        let Coord(x, y) = coord;
+        let left = if x == 0 { max_width() - 1 } else { x - 1 };
+        let right = if x + 1 >= max_width() { 0 } else { x + 1 };
+        let down = if y == 0 { max_height() - 1 } else { y - 1 };
+        let up = if y + 1 >= max_height() { 0 } else { y + 1 };
+        return [
+            Coord(x, up),
+            Coord(x, down),
+            Coord(left, y),
+            Coord(right, y),
+            Coord(right, up),
+            Coord(left, up),
+            Coord(left, down),
+            Coord(right, down),
+        ];
+    }
 
-        coords
+    let offsets = [
+        Coord(0, 1),
+        Coord(0, -1),
+        Coord(-1, 0),
+        Coord(1, 0),
+        Coord(1, 1),
+        Coord(-1, 1),
+        Coord(-1, -1),
+        Coord(1, -1),
+    ];
+
+    let mut neighbors = [coord; 8];
+    neighbors
+        .iter_mut()
+        .zip(offsets.iter())
+        .for_each(|(neighbor, &offset)| *neighbor = coord + offset);
+    neighbors
+}
+
+fn move_agent<B: Board + ?Sized>(
+    board: &mut B,
+    from: Coord,
+    to: Coord,
+    kind: CellKind,
+    count_downs: (i32, i32),
+    age: u32,
+    traits: Traits,
+) {
+    board.set(to.as_idx(), kind, count_downs, age, traits);
+    board.clear(from.as_idx());
+}
+
+// An agent about to breed, once it lived this tick
+struct Parent {
+    kind: AgentKind,
+    // Fed again when it eats
+    starve_count_down: i32,
+    age: u32,
+    traits: Traits,
+    // Tick being run, the young are not updated before the next one
+    tick: u64,
+}
+
+// The parent starts waiting again on `at` and its first young is born on `first`, the
+// others of the litter take the `free` cells still empty. Each young inherits the traits
+// of the parent. Returns the number of young
+fn breed_agent<B: Board + ?Sized>(
+    board: &mut B,
+    at: Coord,
+    first: Coord,
+    parent: &Parent,
+    free: &[Coord],
+) -> u32 {
+    let kind = CellKind::from(parent.kind);
+    let breed_time = get_breed_time_of(parent.kind, at, &parent.traits);
+    let count_downs = (breed_time, parent.starve_count_down);
+    board.set(at.as_idx(), kind, count_downs, parent.age, parent.traits);
+    let born = |board: &mut B, cell: Coord| {
+        let traits = evolution::inherit(parent.traits);
+        let count_downs = birth_count_downs(parent.kind, cell, &traits);
+        board.set(cell.as_idx(), kind, count_downs, 0, traits);
+        board.stamp(cell.as_idx(), parent.tick);
+    };
+    born(board, first);
+
+    let litter = get_breeding(parent.kind).litter;
+    let mut young = 1;
+    for &cell in free.iter() {
+        if young >= litter {
+            break;
+        }
+        if board.kind(cell.as_idx()) == CellKind::Empty {
+            born(board, cell);
+            young += 1;
+        }
     }
+    young
 }
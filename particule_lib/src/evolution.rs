@@ -0,0 +1,56 @@
+use crate::config::{Evolution, Traits};
+use crate::get_evolution;
+use crate::rng::with_rng;
+
+use rand::Rng;
+
+// Bounds of the traits. Vision and speed are how far an agent reaches during its update,
+// tiles updated in parallel are kept farther apart than that, see `parallel`
+pub const MIN_FACTOR: f32 = 0.1;
+pub const MAX_FACTOR: f32 = 10.0;
+pub const MAX_VISION: u8 = 4;
+pub const MAX_SPEED: u8 = 3;
+
+// Traits of a young born from a parent with `traits`, mutated when evolution is on
+pub fn inherit(traits: Traits) -> Traits {
+    match get_evolution() {
+        Some(evolution) => mutate(traits, &evolution),
+        None => traits,
+    }
+}
+
+// Each trait changes with a chance of `evolution.rate`: factors by up to `evolution.size`
+// of their value, vision and speed by one cell. Traits stay within their bounds
+pub fn mutate(traits: Traits, evolution: &Evolution) -> Traits {
+    with_rng(|rng| {
+        let mut mutates = || rng.gen::<f32>() < evolution.rate;
+        let (breed, starve, vision, speed) = (mutates(), mutates(), mutates(), mutates());
+
+        let mut factor = |value: f32, mutates: bool| {
+            if !mutates || evolution.size <= 0.0 {
+                return value;
+            }
+            let change = rng.gen_range(-evolution.size, evolution.size);
+            (value * (1.0 + change)).clamp(MIN_FACTOR, MAX_FACTOR)
+        };
+        let breed = factor(traits.breed, breed);
+        let starve = factor(traits.starve, starve);
+
+        let mut step = |value: u8, mutates: bool, min: u8, max: u8| {
+            if !mutates {
+                return value;
+            }
+            if rng.gen::<bool>() {
+                value.saturating_add(1).min(max)
+            } else {
+                value.saturating_sub(1).max(min)
+            }
+        };
+        Traits {
+            breed,
+            starve,
+            vision: step(traits.vision, vision, 0, MAX_VISION),
+            speed: step(traits.speed, speed, 1, MAX_SPEED),
+        }
+    })
+}
@@ -0,0 +1,148 @@
+use crate::config::{ExportConfig, ExportFormat};
+use crate::environment::Environment;
+use crate::Rgba;
+
+use gif::{Encoder, Frame, Repeat, SetParameter};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+const BACKGROUND: Rgba = [255, 255, 255, 255];
+// Delay between two gif frames in hundredths of a second
+const GIF_FRAME_DELAY: u16 = 10;
+
+// Writes the board to image files as the simulation runs
+pub struct FrameExporter {
+    config: ExportConfig,
+    width: u32,
+    height: u32,
+    gif: Option<Encoder<BufWriter<File>>>,
+    pub frames: u64,
+}
+
+impl FrameExporter {
+    pub fn new(config: &ExportConfig, environment: &Environment) -> io::Result<FrameExporter> {
+        let scale = config.scale.max(1);
+        let width = environment.width as u32 * scale;
+        let height = environment.height as u32 * scale;
+
+        let gif = match config.format {
+            ExportFormat::Png => {
+                fs::create_dir_all(&config.path)?;
+                None
+            }
+            ExportFormat::Gif => {
+                if width > u16::MAX as u32 || height > u16::MAX as u32 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "gif frames are limited to 65535 pixels per side",
+                    ));
+                }
+
+                let file = BufWriter::new(File::create(&config.path)?);
+                let mut encoder = Encoder::new(file, width as u16, height as u16, &[])?;
+                encoder.set(Repeat::Infinite)?;
+                Some(encoder)
+            }
+        };
+
+        Ok(FrameExporter {
+            config: config.clone(),
+            width,
+            height,
+            gif,
+            frames: 0,
+        })
+    }
+
+    // Capture the board once `tick` is done if it is one of the exported ticks, frames are
+    // numbered after their tick
+    pub fn capture(&mut self, tick: u64, environment: &Environment) -> io::Result<()> {
+        if !tick.is_multiple_of(self.config.every.max(1) as u64) {
+            return Ok(());
+        }
+
+        let pixels = self.pixels(environment);
+        match &mut self.gif {
+            None => {
+                let path = PathBuf::from(&self.config.path).join(format!("frame_{:06}.png", tick));
+                write_png(path, self.width, self.height, &pixels)?;
+            }
+            Some(encoder) => {
+                let frame = gif_frame(self.width as u16, self.height as u16, &pixels);
+                encoder.write_frame(&frame)?;
+            }
+        }
+
+        self.frames += 1;
+        Ok(())
+    }
+
+    // Scaled RGBA image of the board, y axis pointing up like on screen
+    fn pixels(&self, environment: &Environment) -> Vec<Rgba> {
+        let colors = environment.colors();
+        let scale = self.config.scale.max(1) as usize;
+        let board_width = environment.width as usize;
+        let board_height = environment.height as usize;
+
+        let mut pixels = Vec::with_capacity((self.width * self.height) as usize);
+        for row in 0..self.height as usize {
+            let y = board_height - 1 - row / scale;
+            for column in 0..self.width as usize {
+                let color = colors[y * board_width + column / scale];
+                pixels.push(if color[3] == 0 { BACKGROUND } else { color });
+            }
+        }
+
+        pixels
+    }
+}
+
+pub(crate) fn write_png<P: AsRef<Path>>(
+    path: P,
+    width: u32,
+    height: u32,
+    pixels: &[Rgba],
+) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels.concat())?;
+    Ok(())
+}
+
+// Boards only use a handful of colors so frames are indexed with a local palette,
+// falling back to gif quantization when there are too many of them
+fn gif_frame(width: u16, height: u16, pixels: &[Rgba]) -> Frame<'static> {
+    let mut palette: HashMap<Rgba, u8> = HashMap::new();
+    let mut indices = Vec::with_capacity(pixels.len());
+    for color in pixels {
+        let next = palette.len();
+        if next > 255 && !palette.contains_key(color) {
+            let mut rgba = pixels.concat();
+            let mut frame = Frame::from_rgba_speed(width, height, &mut rgba, 10);
+            frame.delay = GIF_FRAME_DELAY;
+            return frame;
+        }
+        indices.push(*palette.entry(*color).or_insert(next as u8));
+    }
+
+    let mut colors = vec![0; palette.len() * 3];
+    palette.iter().for_each(|(color, idx)| {
+        let idx = *idx as usize * 3;
+        colors[idx..idx + 3].copy_from_slice(&color[..3]);
+    });
+
+    Frame {
+        width,
+        height,
+        delay: GIF_FRAME_DELAY,
+        palette: Some(colors),
+        buffer: Cow::Owned(indices),
+        ..Frame::default()
+    }
+}
@@ -0,0 +1,137 @@
+use crate::config::{HabitatLayer, Shape, Side};
+use crate::map;
+use crate::placement::PlacementError;
+use crate::Coord;
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+// Per cell habitat, `None` when every cell uses the global times
+static HABITAT: RwLock<Option<Habitat>> = RwLock::new(None);
+// Set with `HABITAT`, spares ticks the lock when there is no habitat
+static HAS_HABITAT: AtomicBool = AtomicBool::new(false);
+
+// Factors applied to the global breed and starve times, 1 keeps them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Factors {
+    pub fish_breed: f32,
+    pub shark_breed: f32,
+    pub shark_starve: f32,
+}
+
+impl Factors {
+    const NEUTRAL: Factors = Factors {
+        fish_breed: 1.0,
+        shark_breed: 1.0,
+        shark_starve: 1.0,
+    };
+}
+
+pub struct Habitat {
+    width: i32,
+    pub cells: Vec<Factors>,
+}
+
+impl Habitat {
+    // Layers multiply each other, a layer applies its full factors where its shape
+    // has a strength of 1 and fades out toward 0
+    pub fn new(
+        layers: &[HabitatLayer],
+        width: i32,
+        height: i32,
+    ) -> Result<Habitat, PlacementError> {
+        let mut cells = vec![Factors::NEUTRAL; (width * height) as usize];
+
+        for layer in layers {
+            let strength = strength(&layer.shape, width, height)?;
+            cells
+                .iter_mut()
+                .zip(strength.iter())
+                .for_each(|(factors, &strength)| {
+                    let blend = |factor: f32| 1.0 + (factor - 1.0) * strength;
+                    factors.fish_breed *= blend(layer.fish_breed);
+                    factors.shark_breed *= blend(layer.shark_breed);
+                    factors.shark_starve *= blend(layer.shark_starve);
+                });
+        }
+
+        Ok(Habitat { width, cells })
+    }
+
+    fn at(&self, coord: Coord) -> Factors {
+        self.cells[(coord.1 * self.width + coord.0) as usize]
+    }
+}
+
+// Strength in [0, 1] of a shape on each cell
+fn strength(shape: &Shape, width: i32, height: i32) -> Result<Vec<f32>, PlacementError> {
+    let cells = (width * height) as usize;
+    let position = |idx: usize| (idx as i32 % width, idx as i32 / width);
+
+    Ok(match shape {
+        Shape::Everywhere => vec![1.0; cells],
+        Shape::Zone {
+            x,
+            y,
+            width: zone_width,
+            height: zone_height,
+        } => (0..cells)
+            .map(|idx| {
+                let (cx, cy) = position(idx);
+                let inside = cx >= *x && cx < x + zone_width && cy >= *y && cy < y + zone_height;
+                if inside {
+                    1.0
+                } else {
+                    0.0
+                }
+            })
+            .collect(),
+        Shape::Gradient { toward } => (0..cells)
+            .map(|idx| {
+                let (cx, cy) = position(idx);
+                let ratio = |value: i32, size: i32| value as f32 / (size - 1).max(1) as f32;
+                match toward {
+                    Side::Left => 1.0 - ratio(cx, width),
+                    Side::Right => ratio(cx, width),
+                    Side::Bottom => 1.0 - ratio(cy, height),
+                    Side::Top => ratio(cy, height),
+                }
+            })
+            .collect(),
+        // Digits 0 to 9 in a text map, `.` is 0, dark pixels are strong in a png image
+        Shape::Map { path } => map::read_layer(
+            Path::new(path),
+            width,
+            height,
+            |c| match c {
+                '.' => Some(0.0),
+                _ => c.to_digit(10).map(|digit| digit as f32 / 9.0),
+            },
+            |pixel| (1.0 - map::brightness(pixel)) * pixel[3] as f32 / 255.0,
+        )?,
+    })
+}
+
+// Replace the habitat of the running simulation, no layers means uniform times
+pub fn set(habitat: Option<Habitat>) {
+    let mut current = HABITAT.write().unwrap();
+    HAS_HABITAT.store(habitat.is_some(), Ordering::Relaxed);
+    *current = habitat;
+}
+
+pub fn factors_at(coord: Coord) -> Factors {
+    if !HAS_HABITAT.load(Ordering::Relaxed) {
+        return Factors::NEUTRAL;
+    }
+
+    HABITAT
+        .read()
+        .unwrap()
+        .as_ref()
+        .map_or(Factors::NEUTRAL, |habitat| habitat.at(coord))
+}
+
+pub(crate) fn scale(time: i32, factor: f32) -> i32 {
+    (time as f32 * factor).round().max(0.0) as i32
+}
@@ -0,0 +1,281 @@
+use crate::config::Traits;
+use crate::environment::{Board, Environment};
+use crate::trace::TickTrace;
+use crate::AgentKind;
+use crate::CellKind;
+use crate::Coord;
+use crate::Decision;
+use std::fmt;
+
+// Violations listed when a tick fails, a broken rule usually breaks the whole board
+const REPORTED: usize = 20;
+
+// Checks of the board run on every tick with the `invariants` feature, a tick
+// breaking one of them panics with what went wrong
+#[derive(Clone, Debug)]
+pub struct Violation {
+    // The decision being applied, none for checks of the whole board
+    pub decision: Option<Decision>,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.decision {
+            Some(decision) => write!(f, "{:?}: {}", decision, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+// What `to` held before a decision moved an agent there
+pub(crate) fn target<B: Board + ?Sized>(board: &B, decision: &Decision) -> Option<CellKind> {
+    decision.get_destination().map(|to| board.kind(to.as_idx()))
+}
+
+// The agent of kind `agent_kind` on `from` applied `decision`, `target` is what
+// the cell it went to held before
+pub(crate) fn check_decision<B: Board + ?Sized>(
+    board: &B,
+    agent_kind: AgentKind,
+    decision: &Decision,
+    neighbors: &[Coord; 8],
+    target: Option<CellKind>,
+    trace: &mut TickTrace,
+) {
+    let from = *decision.get_origin();
+    let kind = CellKind::from(agent_kind);
+    let mut violation = |message: String| {
+        trace.violations.push(Violation {
+            decision: Some(*decision),
+            message,
+        })
+    };
+
+    // Kinds `from` and `to` must hold once the decision is applied
+    let (entered, after) = match decision {
+        Decision::Stall(_) => (None, kind),
+        Decision::Starve(_) | Decision::Die(_) => (None, CellKind::Empty),
+        Decision::Move(..) => (Some(CellKind::Empty), CellKind::Empty),
+        Decision::MoveAndBreed(..) => (Some(CellKind::Empty), kind),
+        Decision::EatAndMove(..) => (Some(CellKind::Fish), CellKind::Empty),
+        Decision::EatAndBreed(..) => (Some(CellKind::Fish), kind),
+    };
+
+    if let Some(&to) = decision.get_destination() {
+        if to == from || !neighbors.contains(&to) {
+            violation(format!("{:?} is not next to {:?}", to, from));
+        }
+        if target != entered {
+            violation(format!(
+                "{:?} held {:?} instead of {:?}",
+                to,
+                target.unwrap_or(CellKind::Empty),
+                entered.unwrap_or(CellKind::Empty)
+            ));
+        }
+        let landed = board.kind(to.as_idx());
+        if landed != kind {
+            violation(format!(
+                "{:?} holds {:?} instead of the {:?}",
+                to, landed, kind
+            ));
+        }
+    }
+    let left = board.kind(from.as_idx());
+    if left != after {
+        violation(format!(
+            "{:?} holds {:?} instead of {:?}",
+            from, left, after
+        ));
+    }
+}
+
+// Population of the board before a tick
+pub(crate) struct Census {
+    fish: i64,
+    sharks: i64,
+    walls: usize,
+}
+
+impl Census {
+    pub(crate) fn of(environment: &Environment) -> Census {
+        Census {
+            fish: environment.count(AgentKind::Fish) as i64,
+            sharks: environment.count(AgentKind::Shark) as i64,
+            walls: walls(environment),
+        }
+    }
+}
+
+fn walls(environment: &Environment) -> usize {
+    environment
+        .kinds
+        .iter()
+        .filter(|&&kind| kind == CellKind::Wall)
+        .count()
+}
+
+// Check the whole board once tick `tick` is done and panic with every violation found
+pub(crate) fn check_tick(environment: &Environment, tick: u64, before: &Census, trace: &TickTrace) {
+    let mut violations = trace.violations.clone();
+    let mut violation = |message: String| {
+        violations.push(Violation {
+            decision: None,
+            message,
+        })
+    };
+
+    // Cells are built from their index, they are where it says by construction. What
+    // can break is the per-field arrays falling out of step with the kinds
+    let size = environment.size();
+    let lengths = [
+        ("breed count downs", environment.breed_count_downs.len()),
+        ("starve count downs", environment.starve_count_downs.len()),
+        ("ages", environment.ages.len()),
+        ("traits", environment.traits.len()),
+        ("update ticks", environment.updated.len()),
+    ];
+    for &(field, len) in lengths.iter() {
+        if len != size {
+            violation(format!("{} {} for {} cells", len, field, size));
+        }
+    }
+    let checked = lengths.iter().map(|&(_, len)| len).fold(size, usize::min);
+
+    for idx in 0..checked {
+        let coord = Coord::from_idx(idx as i32);
+        let kind = environment.kind(idx);
+        if kind != CellKind::Empty && kind != CellKind::Wall {
+            continue;
+        }
+        let (breed, starve) = environment.count_downs(idx);
+        if (breed, starve) != (0, 0) {
+            violation(format!(
+                "{:?} holds {:?} with count downs ({}, {})",
+                coord, kind, breed, starve
+            ));
+        }
+        let age = environment.age(idx);
+        if age != 0 {
+            violation(format!("{:?} holds {:?} aged {}", coord, kind, age));
+        }
+        let traits = environment.traits(idx);
+        if traits != Traits::DEFAULT {
+            violation(format!(
+                "{:?} holds {:?} with traits {:?}",
+                coord, kind, traits
+            ));
+        }
+    }
+
+    let fish = environment.count(AgentKind::Fish) as i64;
+    if fish != before.fish + trace.fish_birth - trace.fish_death {
+        violation(format!(
+            "{} fish became {} with {} births and {} deaths",
+            before.fish, fish, trace.fish_birth, trace.fish_death
+        ));
+    }
+    let sharks = environment.count(AgentKind::Shark) as i64;
+    if sharks != before.sharks + trace.shark_birth - trace.shark_death {
+        violation(format!(
+            "{} sharks became {} with {} births and {} deaths",
+            before.sharks, sharks, trace.shark_birth, trace.shark_death
+        ));
+    }
+    let after = walls(environment);
+    if after != before.walls {
+        violation(format!("{} walls became {}", before.walls, after));
+    }
+
+    if !violations.is_empty() {
+        let mut report: Vec<String> = violations
+            .iter()
+            .take(REPORTED)
+            .map(ToString::to_string)
+            .collect();
+        if violations.len() > REPORTED {
+            report.push(format!("and {} more", violations.len() - REPORTED));
+        }
+        panic!(
+            "tick {} broke the board invariants:\n{}",
+            tick,
+            report.join("\n")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::update_cell;
+    use crate::sma::Sma;
+
+    // A board that loses every write to the cell `stuck`
+    struct Stuck {
+        environment: Environment,
+        stuck: usize,
+    }
+
+    impl Board for Stuck {
+        fn kind(&self, idx: usize) -> CellKind {
+            self.environment.kind(idx)
+        }
+
+        fn count_downs(&self, idx: usize) -> (i32, i32) {
+            self.environment.count_downs(idx)
+        }
+
+        fn age(&self, idx: usize) -> u32 {
+            self.environment.age(idx)
+        }
+
+        fn traits(&self, idx: usize) -> Traits {
+            self.environment.traits(idx)
+        }
+
+        fn updated(&self, idx: usize) -> u64 {
+            self.environment.updated(idx)
+        }
+
+        fn stamp(&mut self, idx: usize, tick: u64) {
+            self.environment.stamp(idx, tick)
+        }
+
+        fn set(
+            &mut self,
+            idx: usize,
+            kind: CellKind,
+            count_downs: (i32, i32),
+            age: u32,
+            traits: Traits,
+        ) {
+            if idx != self.stuck {
+                self.environment.set(idx, kind, count_downs, age, traits)
+            }
+        }
+    }
+
+    #[test]
+    fn moves_that_do_not_land_are_reported() {
+        // A fish walled in on a 3 x 3 closed board but for the cell on its right
+        let mut sma = Sma::new(3, 3, 4, 10, 4, false);
+        (0..9)
+            .filter(|&idx| idx != 4 && idx != 5)
+            .for_each(|idx| sma.environment.add_wall(Coord::from_idx(idx)));
+        sma.environment.spawn(AgentKind::Fish, Coord(1, 1));
+        let mut board = Stuck {
+            environment: sma.environment,
+            stuck: Coord(2, 1).as_idx(),
+        };
+        let mut trace = TickTrace::new();
+
+        update_cell(&mut board, Coord(1, 1).as_idx(), 1, &mut trace);
+
+        let messages: Vec<String> = trace.violations.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            ["Move(Coord(1, 1), Coord(2, 1)): Coord(2, 1) holds Empty instead of the Fish"]
+        );
+    }
+}
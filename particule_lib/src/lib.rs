@@ -1,9 +1,24 @@
+pub mod analysis;
+pub mod config;
 pub mod environment;
+pub mod evolution;
+#[cfg(feature = "images")]
+pub mod export;
+pub mod habitat;
+#[cfg(feature = "invariants")]
+pub mod invariants;
+pub mod lotka_volterra;
+pub mod map;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod placement;
+pub mod rng;
 pub mod wator;
 pub mod sma;
 pub mod trace;
 pub mod pacman;
 
+use config::{Breeding, Evolution, PreyPolicy, Traits};
 use std::convert::TryInto;
 use std::ops;
 
@@ -13,12 +28,27 @@ static mut SHARK_STARVE_COUNT_DOWN: i32 = 0;
 static mut MAX_HEIGTH: i32 = 0;
 static mut MAX_WIDTH: i32 = 0;
 static mut BORDERLESS: bool = false;
+static mut FISH_COLOR: (f32, f32, f32) = (0.0, 1.0, 0.0);
+static mut SHARK_COLOR: (f32, f32, f32) = (1.0, 0.0, 0.0);
+static mut PREY_POLICY: PreyPolicy = PreyPolicy::Random;
+static mut FISH_BREEDING: Breeding = Breeding::DEFAULT;
+static mut SHARK_BREEDING: Breeding = Breeding::DEFAULT;
+static mut FISH_LIFESPAN: Option<u32> = None;
+static mut SHARK_LIFESPAN: Option<u32> = None;
+static mut FISH_TRAITS: Traits = Traits::DEFAULT;
+static mut SHARK_TRAITS: Traits = Traits::DEFAULT;
+static mut EVOLUTION: Option<Evolution> = None;
 
 #[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
 pub struct Coord(pub i32, pub i32);
 
 pub type AgentImpl = Box<dyn Agent>;
 
+// Packed 8 bits per channel color
+pub type Rgba = [u8; 4];
+
+pub const WALL_COLOR: Rgba = [128, 128, 128, 255];
+
 pub trait CloneBoxed {
     fn clone_boxed(&self) -> Box<dyn CloneBoxed>;
 }
@@ -38,11 +68,17 @@ impl Clone for Box<dyn Agent> {
     }
 }
 
-pub trait Agent {
-    fn decide(&self, neighbors: &Vec<Cell>) -> Decision;
+// Agents are updated from several threads with the `parallel` feature
+pub trait Agent: Send + Sync {
+    fn decide(&self, neighbors: &[Cell]) -> Decision;
     fn update(&mut self);
     fn get_kind(&self) -> AgentKind;
     fn get_color(&self) -> (f32, f32, f32);
+    fn get_breed_count_down(&self) -> i32;
+    fn get_starve_count_down(&self) -> i32;
+    // Ticks lived, 0 at birth
+    fn get_age(&self) -> u32;
+    fn get_traits(&self) -> Traits;
     fn coordinate(&self) -> Coord;
     fn set_coordinate(&mut self, coord: Coord);
     fn breed(&mut self) -> AgentImpl;
@@ -50,13 +86,41 @@ pub trait Agent {
     fn clone_boxed(&self) -> Box<dyn Agent>;
 }
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum AgentKind {
     Shark,
     Fish,
 }
 
-#[derive(Debug)]
+// What a cell of the board holds
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CellKind {
+    Empty,
+    Fish,
+    Shark,
+    Wall,
+}
+
+impl CellKind {
+    pub fn agent(self) -> Option<AgentKind> {
+        match self {
+            CellKind::Fish => Some(AgentKind::Fish),
+            CellKind::Shark => Some(AgentKind::Shark),
+            _ => None,
+        }
+    }
+}
+
+impl From<AgentKind> for CellKind {
+    fn from(kind: AgentKind) -> CellKind {
+        match kind {
+            AgentKind::Fish => CellKind::Fish,
+            AgentKind::Shark => CellKind::Shark,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
 pub enum Decision {
     EatAndMove(Coord, Coord),
     EatAndBreed(Coord, Coord),
@@ -64,6 +128,8 @@ pub enum Decision {
     MoveAndBreed(Coord, Coord),
     Starve(Coord),
     Stall(Coord),
+    // Natural death, the agent outlived its lifespan
+    Die(Coord),
 }
 
 impl Decision {
@@ -75,37 +141,49 @@ impl Decision {
             Decision::MoveAndBreed(from, _) => from,
             Decision::Starve(from) => from,
             Decision::Stall(from) => from,
+            Decision::Die(from) => from,
+        }
+    }
+
+    // Where the agent went, none when it stayed or died
+    pub fn get_destination(&self) -> Option<&Coord> {
+        match self {
+            Decision::EatAndMove(_, to) => Some(to),
+            Decision::EatAndBreed(_, to) => Some(to),
+            Decision::Move(_, to) => Some(to),
+            Decision::MoveAndBreed(_, to) => Some(to),
+            Decision::Starve(_) | Decision::Stall(_) | Decision::Die(_) => None,
         }
     }
 }
+
+// Owned copy of a cell, the board itself stores plain values, see `Environment`
 #[derive(Clone)]
 pub enum Cell {
     Empty(Coord),
     Filled(AgentImpl),
+    // Rocks and walls, no agent can enter them
+    Wall(Coord),
 }
 
 impl Cell {
     pub fn is_empty(&self) -> bool {
-        match self {
-            Cell::Empty(_) => true,
-            _ => false,
-        }
+        matches!(self, Cell::Empty(_))
     }
 
     pub fn is_fish(&self) -> bool {
-        match self {
-            Cell::Filled(a) => match a.get_kind() {
-                AgentKind::Fish => true,
-                _ => false,
-            },
-            _ => false,
-        }
+        matches!(self, Cell::Filled(a) if a.get_kind() == AgentKind::Fish)
+    }
+
+    pub fn is_wall(&self) -> bool {
+        matches!(self, Cell::Wall(_))
     }
 
     pub fn to_coord_unchecked(&self) -> Coord {
         match self {
             Cell::Filled(a) => a.coordinate(),
             Cell::Empty(coord) => *coord,
+            Cell::Wall(coord) => *coord,
         }
     }
 }
@@ -193,18 +271,117 @@ impl ops::Mul<Coord> for Coord {
     }
 }
 
-fn get_fish_breed_time() -> i32 {
+pub fn to_rgba(color: (f32, f32, f32)) -> Rgba {
+    [
+        (color.0 * 255.0) as u8,
+        (color.1 * 255.0) as u8,
+        (color.2 * 255.0) as u8,
+        255,
+    ]
+}
+
+pub fn get_fish_breed_time() -> i32 {
     unsafe { FISH_BREED_COUNT_DOWN }
 }
 
-fn get_shark_breed_time() -> i32 {
+pub fn get_shark_breed_time() -> i32 {
     unsafe { SHARK_BREED_COUNT_DOWN }
 }
 
-fn get_shark_starve_time() -> i32 {
+pub fn get_shark_starve_time() -> i32 {
     unsafe { SHARK_STARVE_COUNT_DOWN }
 }
 
+// Times of the habitat at `coord`
+pub fn get_fish_breed_time_at(coord: Coord) -> i32 {
+    habitat::scale(get_fish_breed_time(), habitat::factors_at(coord).fish_breed)
+}
+
+pub fn get_shark_breed_time_at(coord: Coord) -> i32 {
+    habitat::scale(get_shark_breed_time(), habitat::factors_at(coord).shark_breed)
+}
+
+pub fn get_shark_starve_time_at(coord: Coord) -> i32 {
+    habitat::scale(get_shark_starve_time(), habitat::factors_at(coord).shark_starve)
+}
+
+pub fn get_breeding(kind: AgentKind) -> Breeding {
+    unsafe {
+        match kind {
+            AgentKind::Fish => FISH_BREEDING,
+            AgentKind::Shark => SHARK_BREEDING,
+        }
+    }
+}
+
+// Times of an agent with `traits` on `coord`
+pub fn get_breed_time_of(kind: AgentKind, coord: Coord, traits: &Traits) -> i32 {
+    let time = match kind {
+        AgentKind::Fish => get_fish_breed_time_at(coord),
+        AgentKind::Shark => get_shark_breed_time_at(coord),
+    };
+    habitat::scale(time, traits.breed)
+}
+
+pub fn get_starve_time_of(coord: Coord, traits: &Traits) -> i32 {
+    habitat::scale(get_shark_starve_time_at(coord), traits.starve)
+}
+
+// Breed count down of an agent of kind `kind` with `traits` born on `coord`
+pub fn get_maturity_at(kind: AgentKind, coord: Coord, traits: &Traits) -> i32 {
+    match get_breeding(kind).maturity {
+        Some(maturity) => maturity,
+        None => get_breed_time_of(kind, coord, traits),
+    }
+}
+
+// Traits of the agents placed on the board
+pub fn get_traits(kind: AgentKind) -> Traits {
+    unsafe {
+        match kind {
+            AgentKind::Fish => FISH_TRAITS,
+            AgentKind::Shark => SHARK_TRAITS,
+        }
+    }
+}
+
+pub fn get_evolution() -> Option<Evolution> {
+    unsafe { EVOLUTION }
+}
+
+// Ticks an agent of kind `kind` lives at most, forever when none
+pub fn get_lifespan(kind: AgentKind) -> Option<u32> {
+    unsafe {
+        match kind {
+            AgentKind::Fish => FISH_LIFESPAN,
+            AgentKind::Shark => SHARK_LIFESPAN,
+        }
+    }
+}
+
+// Agents younger than the adult age of their species don't breed, whatever their
+// breed count down
+pub fn is_juvenile(kind: AgentKind, age: u32) -> bool {
+    age < get_breeding(kind).adult_age
+}
+
+// An agent dies of old age on its first update once it is `lifespan` ticks old
+pub fn is_past_lifespan(kind: AgentKind, age: u32) -> bool {
+    get_lifespan(kind).is_some_and(|lifespan| age >= lifespan)
+}
+
+pub fn get_prey_policy() -> PreyPolicy {
+    unsafe { PREY_POLICY }
+}
+
+pub fn get_fish_color() -> (f32, f32, f32) {
+    unsafe { FISH_COLOR }
+}
+
+pub fn get_shark_color() -> (f32, f32, f32) {
+    unsafe { SHARK_COLOR }
+}
+
 fn max_height() -> i32 {
     unsafe { MAX_HEIGTH }
 }
@@ -0,0 +1,457 @@
+use crate::trace::Populations;
+use std::cmp::Ordering;
+use std::fmt;
+use std::io::{self, Write};
+
+// Integration steps per tick
+const SUBSTEPS: usize = 4;
+// Unknowns of a fit: the four rates then the fish and sharks to start from
+const UNKNOWNS: usize = 6;
+// Cost evaluations allowed to each search of the unknowns
+const MAX_EVALUATIONS: usize = 6000;
+// Relative spread of the costs of the simplex the search stops at
+const TOLERANCE: f64 = 1e-10;
+// Lowest rate the first guess starts from, the search cannot leave a rate of 0
+const MIN_RATE: f64 = 1e-3;
+// Past this population the model has exploded, the parameters are far off
+const MAX_POPULATION: f64 = 1e12;
+
+// Mean-field predator prey equations, fish are the prey and sharks the predators:
+// fish' = alpha fish - beta fish sharks and sharks' = delta fish sharks - gamma sharks,
+// time in ticks
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Parameters {
+    pub alpha: f64,
+    pub beta: f64,
+    pub gamma: f64,
+    pub delta: f64,
+}
+
+impl Parameters {
+    // Fish and sharks of `len` ticks in a row from `start`, integrated with the fourth
+    // order Runge-Kutta method
+    pub fn integrate(&self, start: (f64, f64), len: usize) -> Vec<(f64, f64)> {
+        let dt = 1.0 / SUBSTEPS as f64;
+        let mut state = start;
+        let mut states = Vec::with_capacity(len);
+        for _ in 0..len {
+            states.push(state);
+            for _ in 0..SUBSTEPS {
+                state = self.step(state, dt);
+            }
+        }
+        states
+    }
+
+    // Fish where the populations stay put, and sharks
+    pub fn equilibrium(&self) -> (f64, f64) {
+        (self.gamma / self.delta, self.alpha / self.beta)
+    }
+
+    fn derivative(&self, (fish, sharks): (f64, f64)) -> (f64, f64) {
+        (
+            self.alpha * fish - self.beta * fish * sharks,
+            self.delta * fish * sharks - self.gamma * sharks,
+        )
+    }
+
+    fn step(&self, (fish, sharks): (f64, f64), dt: f64) -> (f64, f64) {
+        let k1 = self.derivative((fish, sharks));
+        let k2 = self.derivative((fish + dt / 2.0 * k1.0, sharks + dt / 2.0 * k1.1));
+        let k3 = self.derivative((fish + dt / 2.0 * k2.0, sharks + dt / 2.0 * k2.1));
+        let k4 = self.derivative((fish + dt * k3.0, sharks + dt * k3.1));
+        (
+            fish + dt / 6.0 * (k1.0 + 2.0 * k2.0 + 2.0 * k3.0 + k4.0),
+            sharks + dt / 6.0 * (k1.1 + 2.0 * k2.1 + 2.0 * k3.1 + k4.1),
+        )
+    }
+}
+
+impl fmt::Display for Parameters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "alpha {:.4}, beta {:.3e}, gamma {:.4}, delta {:.3e}",
+            self.alpha, self.beta, self.gamma, self.delta
+        )
+    }
+}
+
+// How close the model follows the populations of one species
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Goodness {
+    // Share of the variance of the populations the model explains, 1 for a perfect fit
+    // and below 0 when the mean does better
+    pub r_squared: f64,
+    // Root mean square error in agents
+    pub rmse: f64,
+}
+
+impl Goodness {
+    fn of(observed: &[f64], model: &[f64]) -> Goodness {
+        let mean = observed.iter().sum::<f64>() / observed.len() as f64;
+        let residuals: f64 = observed
+            .iter()
+            .zip(model.iter())
+            .map(|(observed, model)| (observed - model).powi(2))
+            .sum();
+        let total: f64 = observed
+            .iter()
+            .map(|observed| (observed - mean).powi(2))
+            .sum();
+        // Constant populations: only an exact fit explains them
+        let r_squared = if total > 0.0 {
+            1.0 - residuals / total
+        } else if residuals > 0.0 {
+            0.0
+        } else {
+            1.0
+        };
+        Goodness {
+            r_squared,
+            rmse: (residuals / observed.len() as f64).sqrt(),
+        }
+    }
+}
+
+impl fmt::Display for Goodness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "r2 {:.3}, rmse {:.1}", self.r_squared, self.rmse)
+    }
+}
+
+// Solution of the equations closest to the records
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fit {
+    pub parameters: Parameters,
+    // Fish and sharks the solution starts from on the first record
+    pub start: (f64, f64),
+}
+
+impl Fit {
+    // Fish and sharks along the `len` first records
+    pub fn solution(&self, len: usize) -> Vec<(f64, f64)> {
+        self.parameters.integrate(self.start, len)
+    }
+
+    // The search works on logarithms, the unknowns stay positive
+    fn from_logs(logs: &[f64; UNKNOWNS]) -> Fit {
+        Fit {
+            parameters: Parameters {
+                alpha: logs[0].exp(),
+                beta: logs[1].exp(),
+                gamma: logs[2].exp(),
+                delta: logs[3].exp(),
+            },
+            start: (logs[4].exp(), logs[5].exp()),
+        }
+    }
+
+    fn logs(&self) -> [f64; UNKNOWNS] {
+        let parameters = &self.parameters;
+        [
+            parameters.alpha.ln(),
+            parameters.beta.ln(),
+            parameters.gamma.ln(),
+            parameters.delta.ln(),
+            self.start.0.ln(),
+            self.start.1.ln(),
+        ]
+    }
+}
+
+impl fmt::Display for Fit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, from {:.0} fish and {:.0} sharks",
+            self.parameters, self.start.0, self.start.1
+        )
+    }
+}
+
+// Recorded and modelled populations of one tick
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Row {
+    pub tick: u64,
+    pub fish: u64,
+    pub sharks: u64,
+    pub model_fish: f64,
+    pub model_sharks: f64,
+}
+
+// Agent model against the fitted equations
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    pub fit: Fit,
+    pub fish: Goodness,
+    pub sharks: Goodness,
+    pub rows: Vec<Row>,
+}
+
+impl Comparison {
+    // Both series side by side, one line per tick after a header
+    pub fn write_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "tick,fish,sharks,model_fish,model_sharks")?;
+        for row in &self.rows {
+            writeln!(
+                out,
+                "{},{},{},{:.3},{:.3}",
+                row.tick, row.fish, row.sharks, row.model_fish, row.model_sharks
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, fish {}, sharks {}",
+            self.fit, self.fish, self.sharks
+        )
+    }
+}
+
+// Fit the equations to `populations` and follow the solution along the records
+pub fn compare(populations: &Populations) -> Option<Comparison> {
+    let fit = fit(populations)?;
+    let (fish, sharks) = series(populations);
+    let (model_fish, model_sharks): (Vec<f64>, Vec<f64>) =
+        fit.solution(populations.len()).into_iter().unzip();
+
+    let rows = (0..populations.len())
+        .map(|tick| Row {
+            tick: tick as u64,
+            fish: populations.fish[tick],
+            sharks: populations.sharks[tick],
+            model_fish: model_fish[tick],
+            model_sharks: model_sharks[tick],
+        })
+        .collect();
+    Some(Comparison {
+        fit,
+        fish: Goodness::of(&fish, &model_fish),
+        sharks: Goodness::of(&sharks, &model_sharks),
+        rows,
+    })
+}
+
+// Rates and starting populations whose solution is closest to the records, by least
+// squares on both species, each scaled by its own spread. The start is fitted too: runs
+// begin far from the cycle they settle in, which no solution through the first record
+// follows. None with fewer than 3 records or when a species is missing from the first
+// one, the equations would never bring it back
+pub fn fit(populations: &Populations) -> Option<Fit> {
+    if populations.len() < 3 || populations.fish[0] == 0 || populations.sharks[0] == 0 {
+        return None;
+    }
+
+    let (fish, sharks) = series(populations);
+    let objective = |logs: &[f64; UNKNOWNS]| cost(&Fit::from_logs(logs), &fish, &sharks);
+    // The search settles in the closest valley, start it from every guess
+    first_guesses(&fish, &sharks)
+        .iter()
+        .map(|guess| minimize(objective, guess.logs()))
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+        .map(|(logs, _)| Fit::from_logs(&logs))
+}
+
+fn series(populations: &Populations) -> (Vec<f64>, Vec<f64>) {
+    let to_f64 = |counts: &[u64]| counts.iter().map(|&count| count as f64).collect();
+    (to_f64(&populations.fish), to_f64(&populations.sharks))
+}
+
+// Sum of the squared errors of both species over the records, in units of their spread
+fn cost(fit: &Fit, fish: &[f64], sharks: &[f64]) -> f64 {
+    let (fish_scale, shark_scale) = (scale(fish), scale(sharks));
+    let mut sum = 0.0;
+    let model = fit.solution(fish.len());
+    for ((&(model_fish, model_sharks), &fish), &sharks) in model.iter().zip(fish).zip(sharks) {
+        let exploded = |value: f64| !(0.0..=MAX_POPULATION).contains(&value);
+        if exploded(model_fish) || exploded(model_sharks) {
+            return f64::INFINITY;
+        }
+        sum += ((model_fish - fish) / fish_scale).powi(2);
+        sum += ((model_sharks - sharks) / shark_scale).powi(2);
+    }
+    sum
+}
+
+// Standard deviation of the records, their mean when they never change
+fn scale(values: &[f64]) -> f64 {
+    let mean = mean(values);
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / values.len() as f64;
+    if variance > 0.0 {
+        variance.sqrt()
+    } else {
+        mean.max(1.0)
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// Growth rates of each species from one tick to the next against the other species,
+// a straight line through them gives the rates of the equations: fish grow by
+// alpha - beta sharks and sharks by delta fish - gamma. The coupling rates also follow from
+// the time average of a solution, which is the equilibrium. Each guess starts from the
+// first record, the last one from the mean populations as well
+fn first_guesses(fish: &[f64], sharks: &[f64]) -> [Fit; 3] {
+    let mut fish_growth = Vec::new();
+    let mut shark_growth = Vec::new();
+    for tick in 1..fish.len() {
+        let alive = |values: &[f64]| values[tick - 1] > 0.0 && values[tick] > 0.0;
+        if alive(fish) && alive(sharks) {
+            let mid_fish = (fish[tick - 1] + fish[tick]) / 2.0;
+            let mid_sharks = (sharks[tick - 1] + sharks[tick]) / 2.0;
+            fish_growth.push((mid_sharks, (fish[tick] / fish[tick - 1]).ln()));
+            shark_growth.push((mid_fish, (sharks[tick] / sharks[tick - 1]).ln()));
+        }
+    }
+    let (alpha, minus_beta) = line_through(&fish_growth);
+    let (minus_gamma, delta) = line_through(&shark_growth);
+    let alpha = alpha.max(MIN_RATE);
+    let gamma = (-minus_gamma).max(MIN_RATE);
+
+    let (mean_fish, mean_sharks) = (mean(fish).max(1.0), mean(sharks).max(1.0));
+    let at_equilibrium = Parameters {
+        alpha,
+        beta: alpha / mean_sharks,
+        gamma,
+        delta: gamma / mean_fish,
+    };
+    let regression = Parameters {
+        beta: if minus_beta < 0.0 {
+            -minus_beta
+        } else {
+            at_equilibrium.beta
+        },
+        delta: if delta > 0.0 {
+            delta
+        } else {
+            at_equilibrium.delta
+        },
+        ..at_equilibrium
+    };
+    let first = (fish[0], sharks[0]);
+    [
+        Fit {
+            parameters: regression,
+            start: first,
+        },
+        Fit {
+            parameters: at_equilibrium,
+            start: first,
+        },
+        Fit {
+            parameters: at_equilibrium,
+            start: (mean_fish, mean_sharks),
+        },
+    ]
+}
+
+// Intercept and slope of the least squares line through `points`
+fn line_through(points: &[(f64, f64)]) -> (f64, f64) {
+    if points.is_empty() {
+        return (0.0, 0.0);
+    }
+    let count = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / count;
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let slope = if variance > 0.0 {
+        covariance / variance
+    } else {
+        0.0
+    };
+    (mean_y - slope * mean_x, slope)
+}
+
+// Nelder-Mead simplex search of the lowest `cost` from `start`, with its cost
+fn minimize<F: Fn(&[f64; UNKNOWNS]) -> f64>(
+    cost: F,
+    start: [f64; UNKNOWNS],
+) -> ([f64; UNKNOWNS], f64) {
+    let mut simplex: Vec<([f64; UNKNOWNS], f64)> = (0..=UNKNOWNS)
+        .map(|axis| {
+            let mut point = start;
+            if axis < UNKNOWNS {
+                point[axis] += 0.5;
+            }
+            (point, cost(&point))
+        })
+        .collect();
+    let mut evaluations = simplex.len();
+    let towards = |from: &[f64; UNKNOWNS], to: &[f64; UNKNOWNS], factor: f64| {
+        let mut point = *from;
+        point
+            .iter_mut()
+            .zip(to.iter())
+            .for_each(|(from, to)| *from += factor * (to - *from));
+        point
+    };
+
+    while evaluations < MAX_EVALUATIONS {
+        simplex.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        let (best, worst) = (simplex[0].1, simplex[UNKNOWNS].1);
+        if worst - best <= TOLERANCE * (1.0 + best.abs()) {
+            break;
+        }
+
+        let mut centroid = [0.0; UNKNOWNS];
+        for (point, _) in &simplex[..UNKNOWNS] {
+            centroid
+                .iter_mut()
+                .zip(point.iter())
+                .for_each(|(sum, value)| *sum += value / UNKNOWNS as f64);
+        }
+        let worst_point = simplex[UNKNOWNS].0;
+        let reflected = towards(&worst_point, &centroid, 2.0);
+        let reflected_cost = cost(&reflected);
+        evaluations += 1;
+
+        if reflected_cost < best {
+            let expanded = towards(&worst_point, &centroid, 3.0);
+            let expanded_cost = cost(&expanded);
+            evaluations += 1;
+            simplex[UNKNOWNS] = if expanded_cost < reflected_cost {
+                (expanded, expanded_cost)
+            } else {
+                (reflected, reflected_cost)
+            };
+        } else if reflected_cost < simplex[UNKNOWNS - 1].1 {
+            simplex[UNKNOWNS] = (reflected, reflected_cost);
+        } else {
+            let contracted = towards(&worst_point, &centroid, 0.5);
+            let contracted_cost = cost(&contracted);
+            evaluations += 1;
+            if contracted_cost < worst {
+                simplex[UNKNOWNS] = (contracted, contracted_cost);
+            } else {
+                // Shrink everything towards the best point
+                let best_point = simplex[0].0;
+                for vertex in simplex.iter_mut().skip(1) {
+                    let point = towards(&vertex.0, &best_point, 0.5);
+                    *vertex = (point, cost(&point));
+                }
+                evaluations += UNKNOWNS;
+            }
+        }
+    }
+
+    simplex
+        .into_iter()
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+        .unwrap()
+}
@@ -0,0 +1,305 @@
+use crate::environment::Environment;
+use crate::placement::PlacementError;
+use crate::to_rgba;
+use crate::CellKind;
+use crate::Rgba;
+use crate::WALL_COLOR;
+use crate::{get_fish_color, get_shark_color};
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const EMPTY: Rgba = [255, 255, 255, 255];
+
+impl CellKind {
+    pub fn from_char(c: char) -> Option<CellKind> {
+        match c {
+            '.' => Some(CellKind::Empty),
+            'f' => Some(CellKind::Fish),
+            'S' => Some(CellKind::Shark),
+            '#' => Some(CellKind::Wall),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            CellKind::Empty => '.',
+            CellKind::Fish => 'f',
+            CellKind::Shark => 'S',
+            CellKind::Wall => '#',
+        }
+    }
+
+    fn color(self) -> Rgba {
+        match self {
+            CellKind::Empty => EMPTY,
+            CellKind::Fish => to_rgba(get_fish_color()),
+            CellKind::Shark => to_rgba(get_shark_color()),
+            CellKind::Wall => WALL_COLOR,
+        }
+    }
+
+    // Closest tile color, transparent pixels are empty
+    fn from_color(color: Rgba) -> CellKind {
+        if color[3] < 128 {
+            return CellKind::Empty;
+        }
+
+        let distance = |tile: CellKind| -> u32 {
+            tile.color()
+                .iter()
+                .zip(color.iter())
+                .take(3)
+                .map(|(a, b)| (*a as i32 - *b as i32).pow(2) as u32)
+                .sum()
+        };
+        *[
+            CellKind::Empty,
+            CellKind::Fish,
+            CellKind::Shark,
+            CellKind::Wall,
+        ]
+        .iter()
+        .min_by_key(|tile| distance(**tile))
+        .unwrap()
+    }
+}
+
+// A whole board, tiles are in board order: the first one is the bottom left cell.
+// Text maps are `.` empty, `f` fish, `S` shark and `#` wall, first line at the top.
+// In png maps each pixel takes the closest of white, the fish and shark colors and grey.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Map {
+    pub width: i32,
+    pub height: i32,
+    pub tiles: Vec<CellKind>,
+}
+
+impl Map {
+    pub fn parse(text: &str) -> Result<Map, String> {
+        let (columns, rows, chars) = grid(text)?;
+        let tiles = chars
+            .iter()
+            .enumerate()
+            .map(|(idx, &c)| {
+                CellKind::from_char(c).ok_or_else(|| {
+                    format!(
+                        "unknown tile `{}` line {} column {}, expected one of . f S #",
+                        c,
+                        idx / columns + 1,
+                        idx % columns + 1
+                    )
+                })
+            })
+            .collect::<Result<Vec<CellKind>, String>>()?;
+
+        Ok(Map::from_rows(columns, rows, tiles))
+    }
+
+    // Read a text map, or a png image when the file ends with `.png`
+    pub fn read(path: &Path) -> Result<Map, PlacementError> {
+        if is_png(path) {
+            let (columns, rows, pixels) = read_png(path)?;
+            let tiles = pixels.into_iter().map(CellKind::from_color).collect();
+            Ok(Map::from_rows(columns, rows, tiles))
+        } else {
+            Map::parse(&read_text(path)?)
+                .map_err(|err| PlacementError::InvalidMap(path.to_path_buf(), err))
+        }
+    }
+
+    pub fn from_environment(environment: &Environment) -> Map {
+        Map {
+            width: environment.width,
+            height: environment.height,
+            tiles: environment.kinds.clone(),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let width = self.width as usize;
+        let mut text = String::with_capacity(self.tiles.len() + self.height as usize);
+        self.tiles.chunks(width).rev().for_each(|row| {
+            text.extend(row.iter().map(|tile| tile.to_char()));
+            text.push('\n');
+        });
+        text
+    }
+
+    // Write a text map, or a png image when the file ends with `.png`
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        if is_png(path) {
+            self.write_png(path)
+        } else {
+            fs::write(path, self.to_text())
+        }
+    }
+
+    #[cfg(feature = "images")]
+    fn write_png(&self, path: &Path) -> io::Result<()> {
+        let width = self.width as usize;
+        let pixels: Vec<Rgba> = self
+            .tiles
+            .chunks(width)
+            .rev()
+            .flat_map(|row| row.iter().map(|tile| tile.color()))
+            .collect();
+        crate::export::write_png(path, self.width as u32, self.height as u32, &pixels)
+    }
+
+    #[cfg(not(feature = "images"))]
+    fn write_png(&self, _path: &Path) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "png maps need the `images` feature",
+        ))
+    }
+
+    pub fn count(&self, tile: CellKind) -> usize {
+        self.tiles.iter().filter(|t| **t == tile).count()
+    }
+
+    fn from_rows(columns: usize, rows: usize, tiles: Vec<CellKind>) -> Map {
+        Map {
+            width: columns as i32,
+            height: rows as i32,
+            tiles: flip_rows(columns, tiles),
+        }
+    }
+}
+
+// Rows come top to bottom, the board y axis goes up
+fn flip_rows<T: Copy>(columns: usize, values: Vec<T>) -> Vec<T> {
+    values
+        .chunks(columns.max(1))
+        .rev()
+        .flat_map(|row| row.iter().copied())
+        .collect()
+}
+
+// One value per cell read from a text or png map as large as the board, in board order
+pub(crate) fn read_layer<T, C, P>(
+    path: &Path,
+    width: i32,
+    height: i32,
+    from_char: C,
+    from_pixel: P,
+) -> Result<Vec<T>, PlacementError>
+where
+    T: Copy,
+    C: Fn(char) -> Option<T>,
+    P: Fn(Rgba) -> T,
+{
+    let invalid = |message| PlacementError::InvalidMap(path.to_path_buf(), message);
+    let (columns, rows, values) = if is_png(path) {
+        let (columns, rows, pixels) = read_png(path)?;
+        (columns, rows, pixels.into_iter().map(from_pixel).collect())
+    } else {
+        let (columns, rows, chars) = grid(&read_text(path)?).map_err(invalid)?;
+        let values = chars
+            .iter()
+            .enumerate()
+            .map(|(idx, &c)| {
+                from_char(c).ok_or_else(|| {
+                    invalid(format!(
+                        "unexpected `{}` line {} column {}",
+                        c,
+                        idx / columns + 1,
+                        idx % columns + 1
+                    ))
+                })
+            })
+            .collect::<Result<Vec<T>, PlacementError>>()?;
+        (columns, rows, values)
+    };
+
+    if columns != width as usize || rows != height as usize {
+        return Err(invalid(format!(
+            "the map is {}x{} but the board is {}x{}",
+            columns, rows, width, height
+        )));
+    }
+
+    Ok(flip_rows(columns, values))
+}
+
+// Mean of the color channels in [0, 1]
+pub(crate) fn brightness(pixel: Rgba) -> f32 {
+    (pixel[0] as f32 + pixel[1] as f32 + pixel[2] as f32) / (3.0 * 255.0)
+}
+
+pub(crate) fn is_png(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+}
+
+// Characters of a rectangular text, top line first
+pub(crate) fn grid(text: &str) -> Result<(usize, usize, Vec<char>), String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let columns = lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    if let Some(row) = lines
+        .iter()
+        .position(|line| line.chars().count() != columns)
+    {
+        return Err(format!(
+            "line {} is not {} characters long",
+            row + 1,
+            columns
+        ));
+    }
+
+    let chars = lines.iter().flat_map(|line| line.chars()).collect();
+    Ok((columns, lines.len(), chars))
+}
+
+pub(crate) fn read_text(path: &Path) -> Result<String, PlacementError> {
+    fs::read_to_string(path).map_err(|err| PlacementError::MapIo(path.to_path_buf(), err))
+}
+
+// Pixels of a png image, top row first
+#[cfg(feature = "images")]
+pub(crate) fn read_png(path: &Path) -> Result<(usize, usize, Vec<Rgba>), PlacementError> {
+    let invalid =
+        |err: png::DecodingError| PlacementError::InvalidMap(path.to_path_buf(), err.to_string());
+    let file =
+        fs::File::open(path).map_err(|err| PlacementError::MapIo(path.to_path_buf(), err))?;
+
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let (info, mut reader) = decoder.read_info().map_err(invalid)?;
+    let mut data = vec![0; info.buffer_size()];
+    reader.next_frame(&mut data).map_err(invalid)?;
+
+    let channels = match reader.output_color_type().0 {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::RGB => 3,
+        _ => 4,
+    };
+    let pixels = data
+        .chunks(channels)
+        .map(|pixel| match channels {
+            1 => [pixel[0], pixel[0], pixel[0], 255],
+            2 => [pixel[0], pixel[0], pixel[0], pixel[1]],
+            3 => [pixel[0], pixel[1], pixel[2], 255],
+            _ => [pixel[0], pixel[1], pixel[2], pixel[3]],
+        })
+        .collect();
+    Ok((info.width as usize, info.height as usize, pixels))
+}
+
+#[cfg(not(feature = "images"))]
+pub(crate) fn read_png(path: &Path) -> Result<(usize, usize, Vec<Rgba>), PlacementError> {
+    Err(PlacementError::InvalidMap(
+        path.to_path_buf(),
+        "png maps need the `images` feature".into(),
+    ))
+}
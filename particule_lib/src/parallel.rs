@@ -0,0 +1,189 @@
+use crate::config::Traits;
+use crate::environment::{update_cell, Board, Environment};
+use crate::evolution::{MAX_SPEED, MAX_VISION};
+#[cfg(feature = "invariants")]
+use crate::invariants::{self, Census};
+use crate::rng;
+use crate::rng::with_rng;
+use crate::trace::TickTrace;
+use crate::CellKind;
+
+use rand::Rng;
+use rayon::prelude::*;
+use std::ops::Range;
+
+// Side of the tiles the board is cut into
+const TILE_SIZE: i32 = 64;
+// An agent writes the cells up to its speed away and reads the cells it sees from there,
+// tiles updated together must stay farther apart than both
+const MIN_TILE_SIZE: i32 = 2 * MAX_SPEED as i32 + MAX_VISION as i32;
+
+impl Environment {
+    // Same rules as `update_all` with the board cut in tiles updated in four phases,
+    // like the squares of a checkerboard: tiles of a phase are one tile apart so they
+    // never touch the same cells and run on every core. Each tile draws from its own
+    // generator seeded from the tick, a seed gives the same run whatever the threads do.
+    // Boards too small to cut run `update_all`.
+    pub fn update_all_parallel(&mut self) -> TickTrace {
+        let (columns, rows) = match (tile_bounds(self.width), tile_bounds(self.height)) {
+            (Some(columns), Some(rows)) => (columns, rows),
+            _ => return self.update_all(),
+        };
+
+        #[cfg(feature = "invariants")]
+        let census = Census::of(self);
+
+        let tick_seed: u64 = with_rng(|rng| rng.gen());
+        let board = SharedBoard {
+            kinds: self.kinds.as_mut_ptr(),
+            breed_count_downs: self.breed_count_downs.as_mut_ptr(),
+            starve_count_downs: self.starve_count_downs.as_mut_ptr(),
+            ages: self.ages.as_mut_ptr(),
+            traits: self.traits.as_mut_ptr(),
+            updated: self.updated.as_mut_ptr(),
+            len: self.size(),
+        };
+        let width = self.width as usize;
+        let tick = self.ticks + 1;
+
+        let mut trace = TickTrace::new();
+        for phase in 0..4 {
+            let tiles: Vec<Tile> = tiles(&columns, &rows)
+                .filter(|tile| tile.phase == phase)
+                .collect();
+
+            let traces: Vec<TickTrace> = tiles
+                .par_iter()
+                .map(|tile| {
+                    let mut board = board;
+                    let mut trace = TickTrace::new();
+                    rng::scoped(tile_seed(tick_seed, tile.index), || {
+                        for y in tile.rows.clone() {
+                            for x in tile.columns.clone() {
+                                update_cell(
+                                    &mut board,
+                                    y as usize * width + x as usize,
+                                    tick,
+                                    &mut trace,
+                                );
+                            }
+                        }
+                    });
+                    trace
+                })
+                .collect();
+
+            traces.iter().for_each(|tile_trace| trace.add(tile_trace));
+        }
+
+        #[cfg(feature = "invariants")]
+        invariants::check_tick(self, tick, &census, &trace);
+        self.ticks += 1;
+        trace
+    }
+}
+
+struct Tile {
+    index: u64,
+    phase: u8,
+    columns: Range<i32>,
+    rows: Range<i32>,
+}
+
+fn tiles<'a>(columns: &'a [i32], rows: &'a [i32]) -> impl Iterator<Item = Tile> + 'a {
+    let tiles_per_row = columns.len() - 1;
+    rows.windows(2).enumerate().flat_map(move |(ty, y)| {
+        columns.windows(2).enumerate().map(move |(tx, x)| Tile {
+            index: (ty * tiles_per_row + tx) as u64,
+            phase: (tx % 2 + 2 * (ty % 2)) as u8,
+            columns: x[0]..x[1],
+            rows: y[0]..y[1],
+        })
+    })
+}
+
+// Tile edges along one side of the board. The tile count is even so phases still
+// alternate where a toric board wraps around
+fn tile_bounds(size: i32) -> Option<Vec<i32>> {
+    let count = (size / TILE_SIZE).max(2) / 2 * 2;
+    if size / count < MIN_TILE_SIZE {
+        return None;
+    }
+    Some((0..=count).map(|tile| tile * size / count).collect())
+}
+
+fn tile_seed(tick_seed: u64, tile: u64) -> u64 {
+    tick_seed ^ tile.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+// Board shared by the tiles of a phase, each one only touches its own cells and
+// the cells its agents reach around them
+#[derive(Clone, Copy)]
+struct SharedBoard {
+    kinds: *mut CellKind,
+    breed_count_downs: *mut i32,
+    starve_count_downs: *mut i32,
+    ages: *mut u32,
+    traits: *mut Traits,
+    updated: *mut u64,
+    len: usize,
+}
+
+// Tiles updated at the same time never reach the same cells, see `update_all_parallel`
+unsafe impl Send for SharedBoard {}
+unsafe impl Sync for SharedBoard {}
+
+impl Board for SharedBoard {
+    fn kind(&self, idx: usize) -> CellKind {
+        assert!(idx < self.len);
+        unsafe { *self.kinds.add(idx) }
+    }
+
+    fn count_downs(&self, idx: usize) -> (i32, i32) {
+        assert!(idx < self.len);
+        unsafe {
+            (
+                *self.breed_count_downs.add(idx),
+                *self.starve_count_downs.add(idx),
+            )
+        }
+    }
+
+    fn age(&self, idx: usize) -> u32 {
+        assert!(idx < self.len);
+        unsafe { *self.ages.add(idx) }
+    }
+
+    fn traits(&self, idx: usize) -> Traits {
+        assert!(idx < self.len);
+        unsafe { *self.traits.add(idx) }
+    }
+
+    fn updated(&self, idx: usize) -> u64 {
+        assert!(idx < self.len);
+        unsafe { *self.updated.add(idx) }
+    }
+
+    fn stamp(&mut self, idx: usize, tick: u64) {
+        assert!(idx < self.len);
+        unsafe { *self.updated.add(idx) = tick }
+    }
+
+    fn set(
+        &mut self,
+        idx: usize,
+        kind: CellKind,
+        count_downs: (i32, i32),
+        age: u32,
+        traits: Traits,
+    ) {
+        assert!(idx < self.len);
+        unsafe {
+            *self.kinds.add(idx) = kind;
+            *self.breed_count_downs.add(idx) = count_downs.0;
+            *self.starve_count_downs.add(idx) = count_downs.1;
+            *self.ages.add(idx) = age;
+            *self.traits.add(idx) = traits;
+        }
+    }
+}
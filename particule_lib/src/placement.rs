@@ -0,0 +1,361 @@
+use crate::config::{Placement, Side};
+use crate::environment::Environment;
+use crate::map;
+use crate::rng::with_rng;
+use crate::AgentKind;
+use crate::CellKind;
+use crate::Coord;
+
+use rand::{seq::SliceRandom, Rng};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Compactness of the `grouped` placement, a round group with a ragged edge
+pub const GROUPED_COMPACTNESS: f32 = 0.3;
+
+// Put `number` agents of one species on the empty cells picked by `placement`
+pub fn place(
+    environment: &mut Environment,
+    kind: AgentKind,
+    number: u32,
+    placement: &Placement,
+) -> Result<(), PlacementError> {
+    if number == 0 {
+        return Ok(());
+    }
+
+    let allowed = area(environment, placement)?;
+    let mut free: Vec<usize> = (0..environment.size())
+        .filter(|&idx| allowed[idx] && environment.kinds[idx] == CellKind::Empty)
+        .collect();
+    if free.len() < number as usize {
+        return Err(PlacementError::NotEnoughRoom(kind, number, free.len()));
+    }
+
+    with_rng(|rng| free.shuffle(rng));
+    match placement {
+        Placement::Grouped => grow(
+            environment,
+            &[(kind, number)],
+            1,
+            GROUPED_COMPACTNESS,
+            free,
+            &allowed,
+        ),
+        Placement::Clusters { count, compactness } => grow(
+            environment,
+            &[(kind, number)],
+            *count as usize,
+            *compactness,
+            free,
+            &allowed,
+        ),
+        Placement::Noise { scale } => {
+            let noise = noise_field(environment.width, environment.height, *scale);
+            let mut weighted: Vec<(f32, usize)> = free
+                .into_iter()
+                .map(|idx| {
+                    // Weighted sampling without replacement, keep the largest ln(u) / weight.
+                    // The power sharpens the contrast between dense and sparse areas
+                    let u: f32 = with_rng(|rng| rng.gen_range(f32::EPSILON, 1.0));
+                    (u.ln() / noise[idx].powi(4).max(1e-6), idx)
+                })
+                .collect();
+            weighted.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+            weighted
+                .iter()
+                .take(number as usize)
+                .for_each(|&(_, idx)| add(environment, kind, idx));
+        }
+        _ => free
+            .iter()
+            .take(number as usize)
+            .for_each(|&idx| add(environment, kind, idx)),
+    }
+
+    Ok(())
+}
+
+// Cells a placement may use
+fn area(environment: &Environment, placement: &Placement) -> Result<Vec<bool>, PlacementError> {
+    let width = environment.width;
+    let height = environment.height;
+    let cells = environment.size();
+
+    let inside = |f: &dyn Fn(i32, i32) -> bool| {
+        (0..cells as i32)
+            .map(|idx| f(idx % width, idx / width))
+            .collect()
+    };
+
+    Ok(match placement {
+        Placement::Stripes {
+            width: band,
+            gap,
+            vertical,
+        } => {
+            let period = (band + gap) as i32;
+            inside(&|x, y| {
+                let position = if *vertical { x } else { y };
+                position % period < *band as i32
+            })
+        }
+        Placement::Half { side } => inside(&|x, y| match side {
+            Side::Left => x < width / 2,
+            Side::Right => x >= width / 2,
+            Side::Bottom => y < height / 2,
+            Side::Top => y >= height / 2,
+        }),
+        Placement::Map { path } => read_mask(Path::new(path), width, height)?,
+        _ => vec![true; cells],
+    })
+}
+
+// Grow a single group anywhere on the board, the agents of each batch in turn:
+// the later ones surround the first ones
+pub fn grouped(environment: &mut Environment, batches: &[(AgentKind, u32)], compactness: f32) {
+    let mut free: Vec<usize> = (0..environment.size())
+        .filter(|&idx| environment.kinds[idx] == CellKind::Empty)
+        .collect();
+    with_rng(|rng| free.shuffle(rng));
+    let allowed = vec![true; environment.size()];
+    grow(environment, batches, 1, compactness, free, &allowed);
+}
+
+// Grow `groups` groups at once from random cells, each one picks its next
+// cell at random on its own frontier, see `Frontiers` for `compactness`.
+// `free` are the empty allowed cells in random order, enough for every batch.
+fn grow(
+    environment: &mut Environment,
+    batches: &[(AgentKind, u32)],
+    groups: usize,
+    compactness: f32,
+    mut free: Vec<usize>,
+    allowed: &[bool],
+) {
+    let mut frontiers = Frontiers::new(groups, environment.size(), compactness);
+    let mut placed = 0;
+
+    for &(kind, number) in batches {
+        for _ in 0..number {
+            let group = placed % groups;
+            placed += 1;
+
+            // A new group starts when there is no frontier yet or it got enclosed
+            let idx = if frontiers.is_empty(group) {
+                loop {
+                    let idx = free.pop().unwrap();
+                    if environment.kinds[idx] == CellKind::Empty {
+                        break idx;
+                    }
+                }
+            } else {
+                frontiers.pick(group)
+            };
+
+            frontiers.remove(idx);
+            add(environment, kind, idx);
+            environment
+                .neighbor_coords(Coord::from_idx(idx as i32))
+                .iter()
+                .map(Coord::as_idx)
+                .filter(|&neighbor| {
+                    allowed[neighbor] && environment.kinds[neighbor] == CellKind::Empty
+                })
+                .for_each(|neighbor| {
+                    if !frontiers.bump(neighbor) {
+                        frontiers.insert(group, neighbor, filled_neighbors(environment, neighbor));
+                    }
+                });
+        }
+    }
+}
+
+// Cells around `idx` holding an agent or a wall
+fn filled_neighbors(environment: &Environment, idx: usize) -> usize {
+    environment
+        .neighbor_coords(Coord::from_idx(idx as i32))
+        .iter()
+        .filter(|neighbor| environment.kinds[neighbor.as_idx()] != CellKind::Empty)
+        .count()
+}
+
+// Empty cells next to each group, sorted by number of filled neighbors. A cell is
+// picked with a weight of exp(2 * compactness * filled neighbors): every cell is as
+// likely at 0 (Eden growth), the most surrounded ones win at 1 and give round
+// compact groups, the least surrounded ones win at -1 and give branching groups.
+struct Frontiers {
+    // Cells of each group by number of filled neighbors
+    cells: Vec<[Vec<usize>; 9]>,
+    // Where each cell of the board is queued, a cell is in one frontier at most
+    slots: Vec<Option<Slot>>,
+    weights: [f64; 9],
+}
+
+#[derive(Clone, Copy)]
+struct Slot {
+    group: u32,
+    filled: u8,
+    position: u32,
+}
+
+impl Frontiers {
+    fn new(groups: usize, size: usize, compactness: f32) -> Frontiers {
+        let mut weights = [0.0; 9];
+        weights.iter_mut().enumerate().for_each(|(filled, weight)| {
+            *weight = (2.0 * compactness as f64 * filled as f64).exp()
+        });
+
+        Frontiers {
+            cells: vec![Default::default(); groups],
+            slots: vec![None; size],
+            weights,
+        }
+    }
+
+    fn is_empty(&self, group: usize) -> bool {
+        self.cells[group].iter().all(Vec::is_empty)
+    }
+
+    // Draw a cell of the frontier of `group` and take it out, the frontier must not be empty
+    fn pick(&mut self, group: usize) -> usize {
+        let buckets = &self.cells[group];
+        let total: f64 = buckets
+            .iter()
+            .zip(self.weights.iter())
+            .map(|(cells, weight)| cells.len() as f64 * weight)
+            .sum();
+
+        let mut draw = with_rng(|rng| rng.gen::<f64>()) * total;
+        let mut picked = None;
+        for (cells, weight) in buckets.iter().zip(self.weights.iter()) {
+            if cells.is_empty() {
+                continue;
+            }
+            let bucket_weight = cells.len() as f64 * weight;
+            picked = Some((cells, weight, draw));
+            if draw < bucket_weight {
+                break;
+            }
+            draw -= bucket_weight;
+        }
+
+        // Rounding may leave the draw past the last cells, the last one is picked then
+        let (cells, weight, draw) = picked.unwrap();
+        let idx = cells[((draw / weight) as usize).min(cells.len() - 1)];
+        self.remove(idx);
+        idx
+    }
+
+    fn insert(&mut self, group: usize, idx: usize, filled: usize) {
+        let cells = &mut self.cells[group][filled];
+        self.slots[idx] = Some(Slot {
+            group: group as u32,
+            filled: filled as u8,
+            position: cells.len() as u32,
+        });
+        cells.push(idx);
+    }
+
+    fn remove(&mut self, idx: usize) -> Option<Slot> {
+        let slot = self.slots[idx].take()?;
+        let cells = &mut self.cells[slot.group as usize][slot.filled as usize];
+        let position = slot.position as usize;
+        cells.swap_remove(position);
+        if let Some(&moved) = cells.get(position) {
+            if let Some(moved) = self.slots[moved].as_mut() {
+                moved.position = position as u32;
+            }
+        }
+        Some(slot)
+    }
+
+    // One more filled cell around `idx`, false when it is in no frontier
+    fn bump(&mut self, idx: usize) -> bool {
+        match self.remove(idx) {
+            Some(slot) => {
+                let filled = (slot.filled as usize + 1).min(8);
+                self.insert(slot.group as usize, idx, filled);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn add(environment: &mut Environment, kind: AgentKind, idx: usize) {
+    environment.spawn(kind, Coord::from_idx(idx as i32));
+}
+
+// Smooth value noise in [0, 1], random values on a lattice of `scale` cells
+// blended with a smoothstep
+pub fn noise_field(width: i32, height: i32, scale: f32) -> Vec<f32> {
+    let columns = (width as f32 / scale).ceil() as usize + 2;
+    let rows = (height as f32 / scale).ceil() as usize + 2;
+    let lattice: Vec<f32> = (0..columns * rows)
+        .map(|_| with_rng(|rng| rng.gen()))
+        .collect();
+
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let mut field = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let fx = x as f32 / scale;
+            let fy = y as f32 / scale;
+            let (x0, y0) = (fx.floor() as usize, fy.floor() as usize);
+            let (tx, ty) = (smooth(fx.fract()), smooth(fy.fract()));
+
+            let at = |column: usize, row: usize| lattice[row * columns + column];
+            let bottom = at(x0, y0) + (at(x0 + 1, y0) - at(x0, y0)) * tx;
+            let top = at(x0, y0 + 1) + (at(x0 + 1, y0 + 1) - at(x0, y0 + 1)) * tx;
+            field.push(bottom + (top - bottom) * ty);
+        }
+    }
+    field
+}
+
+// Marked cells of a map as large as the board, the first row of the map is the top of the board.
+// In a text map every character but `.` and spaces marks a cell, in a png image dark pixels do.
+pub fn read_mask(path: &Path, width: i32, height: i32) -> Result<Vec<bool>, PlacementError> {
+    map::read_layer(
+        path,
+        width,
+        height,
+        |c| Some(c != '.' && c != ' '),
+        |pixel| pixel[3] >= 128 && map::brightness(pixel) < 0.5,
+    )
+}
+
+#[derive(Debug)]
+pub enum PlacementError {
+    // Species, agents to place and free cells in their area
+    NotEnoughRoom(AgentKind, u32, usize),
+    MapIo(PathBuf, io::Error),
+    InvalidMap(PathBuf, String),
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlacementError::NotEnoughRoom(kind, number, free) => write!(
+                f,
+                "unable to place {} {}, only {} free cells in their placement area",
+                number,
+                match kind {
+                    AgentKind::Fish => "fish",
+                    AgentKind::Shark => "sharks",
+                },
+                free
+            ),
+            PlacementError::MapIo(path, err) => {
+                write!(f, "unable to read map {}: {}", path.display(), err)
+            }
+            PlacementError::InvalidMap(path, message) => {
+                write!(f, "invalid map {}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlacementError {}
@@ -0,0 +1,32 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::cell::RefCell;
+
+thread_local! {
+    // Every random draw of the simulation goes through this generator,
+    // seeding it makes a run reproducible
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+pub fn seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+pub fn with_rng<T, F>(f: F) -> T
+where
+    F: FnOnce(&mut StdRng) -> T,
+{
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+// Run `f` with the generator of this thread seeded with `seed`, then put the
+// previous generator back
+pub fn scoped<T, F>(seed: u64, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let previous = RNG.with(|rng| rng.replace(StdRng::seed_from_u64(seed)));
+    let result = f();
+    RNG.with(|rng| *rng.borrow_mut() = previous);
+    result
+}
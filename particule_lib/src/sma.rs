@@ -1,24 +1,67 @@
+use crate::config::{
+    Breeding, Config, Evolution, HabitatLayer, Placement, PreyPolicy, Traits, WhenBlocked,
+};
 use crate::environment::Environment;
-use crate::wator::fish::Fish;
-use crate::wator::shark::Shark;
+use crate::get_evolution;
+use crate::habitat;
+use crate::habitat::Habitat;
+use crate::map::Map;
+use crate::placement;
+use crate::placement::PlacementError;
+use crate::rng;
+use crate::rng::with_rng;
+use crate::trace::{Stats, TickTrace};
+use crate::AgentKind;
+use crate::CellKind;
 use crate::Coord;
 use crate::BORDERLESS;
+use crate::EVOLUTION;
+use crate::FISH_BREEDING;
 use crate::FISH_BREED_COUNT_DOWN;
+use crate::FISH_COLOR;
+use crate::FISH_LIFESPAN;
+use crate::FISH_TRAITS;
 use crate::MAX_HEIGTH;
 use crate::MAX_WIDTH;
+use crate::PREY_POLICY;
+use crate::SHARK_BREEDING;
 use crate::SHARK_BREED_COUNT_DOWN;
+use crate::SHARK_COLOR;
+use crate::SHARK_LIFESPAN;
 use crate::SHARK_STARVE_COUNT_DOWN;
+use crate::SHARK_TRAITS;
+
+use rand::seq::SliceRandom;
+use std::path::Path;
 
-use rand::{seq::SliceRandom, thread_rng, Rng};
 pub struct Sma {
     pub environment: Environment,
 }
 
 impl Sma {
-    pub fn tick(&mut self) {
-        self.environment.update_all();
+    #[cfg(not(feature = "parallel"))]
+    pub fn tick(&mut self) -> TickTrace {
+        self.environment.update_all()
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn tick(&mut self) -> TickTrace {
+        self.environment.update_all_parallel()
     }
 
+    // Ages of the agents on the board, and their traits when they evolve
+    pub fn stats(&self) -> Stats {
+        let traits = |kind| get_evolution().map(|_| self.environment.trait_stats(kind));
+        Stats {
+            shark_ages: self.environment.age_stats(AgentKind::Shark),
+            fish_ages: self.environment.age_stats(AgentKind::Fish),
+            shark_traits: traits(AgentKind::Shark),
+            fish_traits: traits(AgentKind::Fish),
+        }
+    }
+
+    // Sharks hunt, agents breed, live forever and never evolve with the default rules until
+    // told otherwise, and the habitat is the same everywhere
     pub fn new(
         width: i32,
         height: i32,
@@ -34,20 +77,220 @@ impl Sma {
             MAX_HEIGTH = height;
             MAX_WIDTH = width;
             BORDERLESS = borderless;
+            PREY_POLICY = PreyPolicy::Random;
+            FISH_BREEDING = Breeding::DEFAULT;
+            SHARK_BREEDING = Breeding::DEFAULT;
+            FISH_LIFESPAN = None;
+            SHARK_LIFESPAN = None;
+            FISH_TRAITS = Traits::DEFAULT;
+            SHARK_TRAITS = Traits::DEFAULT;
+            EVOLUTION = None;
         }
+        habitat::set(None);
 
         Sma {
             environment: Environment::new(width, height),
         }
     }
 
+    // Change the simulation parameters of a running simulation,
+    // agents pick up the new times on their next breed or meal
+    pub fn set_parameters(
+        &mut self,
+        fish_breed_time: i32,
+        shark_breed_time: i32,
+        shark_starve_time: i32,
+        borderless: bool,
+    ) {
+        unsafe {
+            FISH_BREED_COUNT_DOWN = fish_breed_time;
+            SHARK_BREED_COUNT_DOWN = shark_breed_time;
+            SHARK_STARVE_COUNT_DOWN = shark_starve_time;
+            BORDERLESS = borderless;
+        }
+    }
+
+    pub fn set_colors(&mut self, fish: [f32; 3], shark: [f32; 3]) {
+        set_colors(fish, shark);
+    }
+
+    // Agents waiting to breed in place are only ready once their species stops breeding in place
+    pub fn set_breeding(&mut self, fish: Breeding, shark: Breeding) {
+        unsafe {
+            FISH_BREEDING = fish;
+            SHARK_BREEDING = shark;
+        }
+        [(AgentKind::Fish, fish), (AgentKind::Shark, shark)]
+            .iter()
+            .filter(|(_, breeding)| breeding.when_blocked != WhenBlocked::InPlace)
+            .for_each(|&(kind, _)| self.environment.unblock(kind));
+    }
+
+    // Agents already older than a new lifespan die on their next update
+    pub fn set_lifespans(&mut self, fish: Option<u32>, shark: Option<u32>) {
+        unsafe {
+            FISH_LIFESPAN = fish;
+            SHARK_LIFESPAN = shark;
+        }
+    }
+
+    // Traits of the agents placed from now on
+    pub fn set_traits(&mut self, fish: Traits, shark: Traits) {
+        unsafe {
+            FISH_TRAITS = fish;
+            SHARK_TRAITS = shark;
+        }
+    }
+
+    pub fn set_evolution(&mut self, evolution: Option<Evolution>) {
+        unsafe {
+            EVOLUTION = evolution;
+        }
+    }
+
+    pub fn set_prey_policy(&mut self, policy: PreyPolicy) {
+        unsafe {
+            PREY_POLICY = policy;
+        }
+    }
+
+    // Habitat layers can change while the simulation runs, agents pick up
+    // the new times on their next breed or meal
+    pub fn set_habitat(&mut self, layers: &[HabitatLayer]) -> Result<(), PlacementError> {
+        let habitat = if layers.is_empty() {
+            None
+        } else {
+            Some(Habitat::new(
+                layers,
+                self.environment.width,
+                self.environment.height,
+            )?)
+        };
+        habitat::set(habitat);
+        Ok(())
+    }
+
+    // Seed the random generator when the config asks for it and place the agents
+    pub fn from_config(config: &Config) -> Result<Sma, PlacementError> {
+        if let Some(seed) = config.seed {
+            rng::seed(seed);
+        }
+
+        // Png maps are matched against the configured colors
+        set_colors(config.fish_color, config.shark_color);
+        let map = match &config.map {
+            Some(path) => Some(Map::read(Path::new(path))?),
+            None => None,
+        };
+
+        let (width, height) = match &map {
+            Some(map) => (map.width, map.height),
+            None => (config.x as i32, config.y as i32),
+        };
+        let mut sma = Sma::new(
+            width,
+            height,
+            config.fish_breed_time,
+            config.shark_breed_time,
+            config.shark_starve_time,
+            config.borderless,
+        );
+
+        sma.set_prey_policy(config.prey_policy);
+        sma.set_breeding(
+            config.breeding_of(AgentKind::Fish),
+            config.breeding_of(AgentKind::Shark),
+        );
+        sma.set_lifespans(config.fish_lifespan, config.shark_lifespan);
+        sma.set_traits(config.fish_traits, config.shark_traits);
+        sma.set_evolution(config.evolution);
+        sma.set_habitat(&config.habitat)?;
+
+        // Walls going past the edges of a map are cut
+        config.walls.iter().for_each(|wall| {
+            for y in wall.y.max(0)..(wall.y + wall.height).min(height) {
+                for x in wall.x.max(0)..(wall.x + wall.width).min(width) {
+                    sma.environment.add_wall(Coord(x, y));
+                }
+            }
+        });
+
+        if let Some(map) = map {
+            sma.load_map(&map);
+            return Ok(sma);
+        }
+
+        // Walls may leave less room than the board size checked by `Config::validate`
+        let free = sma
+            .environment
+            .kinds
+            .iter()
+            .filter(|&&kind| kind == CellKind::Empty)
+            .count();
+        if config.fish_number as usize > free {
+            return Err(PlacementError::NotEnoughRoom(
+                AgentKind::Fish,
+                config.fish_number,
+                free,
+            ));
+        }
+        if config.shark_number as usize > free - config.fish_number as usize {
+            return Err(PlacementError::NotEnoughRoom(
+                AgentKind::Shark,
+                config.shark_number,
+                free - config.fish_number as usize,
+            ));
+        }
+
+        let fish = config.fish_placement.as_ref();
+        let shark = config.shark_placement.as_ref();
+        match (&config.placement, fish, shark) {
+            (Placement::Random, None, None) => {
+                sma.gen_agents(config.fish_number, config.shark_number)
+            }
+            // Both species share the same group
+            (Placement::Grouped, None, None) => {
+                sma.gen_agents_grouped(config.fish_number, config.shark_number)
+            }
+            (placement, fish, shark) => {
+                placement::place(
+                    &mut sma.environment,
+                    AgentKind::Fish,
+                    config.fish_number,
+                    fish.unwrap_or(placement),
+                )?;
+                placement::place(
+                    &mut sma.environment,
+                    AgentKind::Shark,
+                    config.shark_number,
+                    shark.unwrap_or(placement),
+                )?;
+            }
+        }
+        Ok(sma)
+    }
+
+    // Put the fish, sharks and walls of a map as large as the board
+    pub fn load_map(&mut self, map: &Map) {
+        map.tiles.iter().enumerate().for_each(|(idx, tile)| {
+            let coordinate = Coord::from_idx(idx as i32);
+            match tile {
+                CellKind::Fish => self.environment.spawn(AgentKind::Fish, coordinate),
+                CellKind::Shark => self.environment.spawn(AgentKind::Shark, coordinate),
+                CellKind::Wall => self.environment.add_wall(coordinate),
+                CellKind::Empty => (),
+            }
+        });
+    }
+
     // Unfortunatly we need to pass a RC to have a shared reference
     pub fn gen_agents(&mut self, fish_number: u32, shark_number: u32) {
         let size = self.environment.height * self.environment.width;
 
-        let mut vec: Vec<i32> = (0..size).collect();
-        let mut rng = thread_rng();
-        vec.shuffle(&mut rng);
+        let mut vec: Vec<i32> = (0..size)
+            .filter(|&idx| self.environment.kinds[idx as usize] == CellKind::Empty)
+            .collect();
+        with_rng(|rng| vec.shuffle(rng));
 
         (0..(fish_number as usize)).for_each(|_| {
             let idx = vec.pop().unwrap();
@@ -55,8 +298,7 @@ impl Sma {
             let y = (idx - x) / self.environment.width;
             let coordinate = Coord(x, y);
 
-            let fish = Fish::new(coordinate);
-            self.environment.add_agent(Box::new(fish));
+            self.environment.spawn(AgentKind::Fish, coordinate);
         });
 
         (0..(shark_number as usize)).for_each(|_| {
@@ -65,32 +307,26 @@ impl Sma {
             let y = (idx - x) / self.environment.width;
             let coordinate = Coord(x, y);
 
-            let shark = Shark::new(coordinate);
-            self.environment.add_agent(Box::new(shark));
+            self.environment.spawn(AgentKind::Shark, coordinate);
         });
     }
 
+    // Both species share the same group, the sharks around the fish
     pub fn gen_agents_grouped(&mut self, fish_number: u32, shark_number: u32) {
-        let size = self.environment.height * self.environment.width;
-        let idx = thread_rng().gen_range(0, size);
-
-        let target = Coord::from_idx(idx);
-        let fish = Fish::new(target);
-        self.environment.add_agent(Box::new(fish));
-        (0..((fish_number - 1) as usize)).for_each(|_| {
-            let available = self.environment.get_adjacent();
-            let neigh_idx = thread_rng().gen_range(0, available.len());
-            let target: Coord = available[neigh_idx];
-            let fish = Fish::new(target);
-            self.environment.add_agent(Box::new(fish));
-        });
+        placement::grouped(
+            &mut self.environment,
+            &[
+                (AgentKind::Fish, fish_number),
+                (AgentKind::Shark, shark_number),
+            ],
+            placement::GROUPED_COMPACTNESS,
+        );
+    }
+}
 
-        (0..(shark_number as usize)).for_each(|_| {
-            let available = self.environment.get_adjacent();
-            let neigh_idx = thread_rng().gen_range(0, available.len());
-            let target: Coord = available[neigh_idx];
-            let shark = Shark::new(target);
-            self.environment.add_agent(Box::new(shark));
-        });
+fn set_colors(fish: [f32; 3], shark: [f32; 3]) {
+    unsafe {
+        FISH_COLOR = (fish[0], fish[1], fish[2]);
+        SHARK_COLOR = (shark[0], shark[1], shark[2]);
     }
 }
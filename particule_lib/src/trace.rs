@@ -1,3 +1,8 @@
+#[cfg(feature = "invariants")]
+use crate::invariants::Violation;
+use crate::config::Traits;
+use crate::environment::Environment;
+use crate::is_juvenile;
 use crate::AgentKind;
 use std::fmt;
 pub struct TickTrace {
@@ -5,6 +10,9 @@ pub struct TickTrace {
     pub shark_death: i64,
     pub fish_birth: i64,
     pub fish_death: i64,
+    // Decisions that left the board inconsistent during the tick
+    #[cfg(feature = "invariants")]
+    pub violations: Vec<Violation>,
 }
 
 impl fmt::Display for TickTrace {
@@ -19,6 +27,8 @@ impl TickTrace {
             shark_death: 0,
             fish_birth: 0,
             fish_death: 0,
+            #[cfg(feature = "invariants")]
+            violations: Vec::new(),
         }
     }
 
@@ -29,6 +39,16 @@ impl TickTrace {
         }
     }
 
+    // Sum of the traces of parts of the board
+    pub fn add(&mut self, other: &TickTrace) {
+        self.shark_birth += other.shark_birth;
+        self.shark_death += other.shark_death;
+        self.fish_birth += other.fish_birth;
+        self.fish_death += other.fish_death;
+        #[cfg(feature = "invariants")]
+        self.violations.extend_from_slice(&other.violations);
+    }
+
     pub fn death(&mut self, agent_kind: AgentKind) {
         match agent_kind {
             AgentKind::Fish => self.fish_death += 1,
@@ -36,3 +56,198 @@ impl TickTrace {
         }
     }
 }
+
+impl Default for TickTrace {
+    fn default() -> TickTrace {
+        TickTrace::new()
+    }
+}
+
+// Fish and sharks on the board, one record per tick in order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Populations {
+    pub fish: Vec<u64>,
+    pub sharks: Vec<u64>,
+}
+
+impl Populations {
+    pub fn push(&mut self, fish: u64, sharks: u64) {
+        self.fish.push(fish);
+        self.sharks.push(sharks);
+    }
+
+    // Populations on the board of `environment`
+    pub fn record(&mut self, environment: &Environment) {
+        self.push(
+            environment.count(AgentKind::Fish) as u64,
+            environment.count(AgentKind::Shark) as u64,
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.fish.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fish.is_empty()
+    }
+}
+
+// Ages and traits of the agents on the board, built by `Sma::stats` only when a frontend
+// shows them since it goes over the whole board
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub shark_ages: AgeStats,
+    pub fish_ages: AgeStats,
+    // When evolution is on
+    pub shark_traits: Option<TraitStats>,
+    pub fish_traits: Option<TraitStats>,
+}
+
+// Ages of both species then their traits when they evolve
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, {}", self.shark_ages, self.fish_ages)?;
+        if let (Some(shark), Some(fish)) = (&self.shark_traits, &self.fish_traits) {
+            write!(f, ", {}, {}", shark, fish)?;
+        }
+        Ok(())
+    }
+}
+
+// Age distribution of the agents of one species
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AgeStats {
+    pub count: u64,
+    // Agents younger than the adult age of their species, see `is_juvenile`
+    pub juveniles: u64,
+    pub mean: f64,
+    // Lower median, 0 without agents
+    pub median: u32,
+    pub max: u32,
+}
+
+impl AgeStats {
+    pub fn of(kind: AgentKind, mut ages: Vec<u32>) -> AgeStats {
+        if ages.is_empty() {
+            return AgeStats::default();
+        }
+
+        let count = ages.len();
+        let sum: u64 = ages.iter().map(|&age| age as u64).sum();
+        let (_, &mut median, _) = ages.select_nth_unstable((count - 1) / 2);
+        AgeStats {
+            count: count as u64,
+            juveniles: ages.iter().filter(|&&age| is_juvenile(kind, age)).count() as u64,
+            mean: sum as f64 / count as f64,
+            median,
+            max: ages.iter().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+// Mean, median and max age
+impl fmt::Display for AgeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1}, {}, {}", self.mean, self.median, self.max)
+    }
+}
+
+// Mean, standard deviation and range of one trait
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Spread {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+// Trait distribution of the agents of one species
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TraitStats {
+    pub count: u64,
+    pub breed: Spread,
+    pub starve: Spread,
+    pub vision: Spread,
+    pub speed: Spread,
+}
+
+impl TraitStats {
+    pub fn of<I: IntoIterator<Item = Traits>>(traits: I) -> TraitStats {
+        let mut sums = [Sums::default(); 4];
+        let mut count = 0;
+        for traits in traits {
+            let values = [
+                traits.breed as f64,
+                traits.starve as f64,
+                traits.vision as f64,
+                traits.speed as f64,
+            ];
+            sums.iter_mut()
+                .zip(values.iter())
+                .for_each(|(sums, &value)| sums.add(value));
+            count += 1;
+        }
+
+        TraitStats {
+            count,
+            breed: sums[0].spread(count),
+            starve: sums[1].spread(count),
+            vision: sums[2].spread(count),
+            speed: sums[3].spread(count),
+        }
+    }
+}
+
+// Mean breed factor, starve factor, vision and speed
+impl fmt::Display for TraitStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.2}, {:.2}, {:.2}, {:.2}",
+            self.breed.mean, self.starve.mean, self.vision.mean, self.speed.mean
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Sums {
+    sum: f64,
+    squares: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for Sums {
+    fn default() -> Sums {
+        Sums {
+            sum: 0.0,
+            squares: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl Sums {
+    fn add(&mut self, value: f64) {
+        self.sum += value;
+        self.squares += value * value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    fn spread(&self, count: u64) -> Spread {
+        if count == 0 {
+            return Spread::default();
+        }
+        let mean = self.sum / count as f64;
+        Spread {
+            mean,
+            // Rounding can take the variance of equal values slightly below 0
+            std_dev: (self.squares / count as f64 - mean * mean).max(0.0).sqrt(),
+            min: self.min,
+            max: self.max,
+        }
+    }
+}
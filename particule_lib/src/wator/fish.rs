@@ -1,51 +1,62 @@
-use crate::get_fish_breed_time;
+use crate::config::Traits;
+use crate::evolution;
+use crate::get_breed_time_of;
+use crate::get_fish_color;
+use crate::get_maturity_at;
+use crate::get_traits;
+use crate::is_juvenile;
+use crate::is_past_lifespan;
+use crate::rng::with_rng;
 use crate::Agent;
 use crate::AgentImpl;
 use crate::AgentKind;
 use crate::Cell;
 use crate::Coord;
 use crate::Decision;
-use std::convert::TryInto;
 
-use rand::{thread_rng, Rng};
+use rand::Rng;
 #[derive(Clone, Debug)]
 pub struct Fish {
     pub coordinate: Coord,
     pub breed_count_down: i32,
+    pub age: u32,
+    pub traits: Traits,
 }
 
 impl Fish {
     pub fn new(coordinate: Coord) -> Fish {
+        let traits = get_traits(AgentKind::Fish);
         Fish {
             coordinate,
-            breed_count_down: get_fish_breed_time(),
+            breed_count_down: get_maturity_at(AgentKind::Fish, coordinate, &traits),
+            age: 0,
+            traits,
         }
     }
 }
 
 impl Agent for Fish {
     fn update(&mut self) {
-        self.breed_count_down -= 1;
+        self.breed_count_down = (self.breed_count_down - 1).max(0);
+        self.age = self.age.saturating_add(1);
     }
 
-    fn decide(&self, neighbors: &Vec<Cell>) -> Decision {
-        if neighbors.is_empty() {
-            Decision::Stall(self.coordinate)
-        } else {
-            let from = self.coordinate;
-            let mut rng = thread_rng();
-            let idx = rng.gen_range(0, neighbors.len());
-            let destination = neighbors[idx].clone().try_into();
-            if let Ok(to) = destination {
-                if self.breed_count_down == 0 {
-                    Decision::MoveAndBreed(from, to)
-                } else {
-                    Decision::Move(from, to)
-                }
-            } else {
-                unreachable!()
-            }
+    // The fish sees no farther than its neighbors whatever its vision
+    fn decide(&self, neighbors: &[Cell]) -> Decision {
+        if is_past_lifespan(AgentKind::Fish, self.age) {
+            return Decision::Die(self.coordinate);
         }
+        let free: Vec<Coord> = neighbors
+            .iter()
+            .filter(|cell| cell.is_empty())
+            .map(Cell::to_coord_unchecked)
+            .collect();
+        let breed_count_down = if is_juvenile(AgentKind::Fish, self.age) {
+            self.breed_count_down.max(1)
+        } else {
+            self.breed_count_down
+        };
+        decide(self.coordinate, breed_count_down, &free)
     }
 
     fn get_kind(&self) -> crate::AgentKind {
@@ -56,14 +67,30 @@ impl Agent for Fish {
     }
 
     fn breed(&mut self) -> AgentImpl {
-        self.breed_count_down = get_fish_breed_time();
+        self.breed_count_down = get_breed_time_of(AgentKind::Fish, self.coordinate, &self.traits);
+        let traits = evolution::inherit(self.traits);
         Box::new(Fish {
             coordinate: self.coordinate,
-            breed_count_down: get_fish_breed_time(),
+            breed_count_down: get_maturity_at(AgentKind::Fish, self.coordinate, &traits),
+            age: 0,
+            traits,
         })
     }
     fn get_color(&self) -> (f32, f32, f32) {
-        (0.0, 1.0, 0.0)
+        get_fish_color()
+    }
+    fn get_breed_count_down(&self) -> i32 {
+        self.breed_count_down
+    }
+    fn get_age(&self) -> u32 {
+        self.age
+    }
+    fn get_traits(&self) -> Traits {
+        self.traits
+    }
+    // Fish never starve
+    fn get_starve_count_down(&self) -> i32 {
+        0
     }
     fn set_coordinate(&mut self, coord: Coord) {
         self.coordinate = coord
@@ -76,3 +103,18 @@ impl Agent for Fish {
         unreachable!("Fish don't starve")
     }
 }
+
+// A fish moves to one of the `free` cells around it at random, and breeds
+// once its count down reached 0, see `config::Breeding`
+pub fn decide(from: Coord, breed_count_down: i32, free: &[Coord]) -> Decision {
+    if free.is_empty() {
+        return Decision::Stall(from);
+    }
+
+    let to = free[with_rng(|rng| rng.gen_range(0, free.len()))];
+    if breed_count_down <= 0 {
+        Decision::MoveAndBreed(from, to)
+    } else {
+        Decision::Move(from, to)
+    }
+}
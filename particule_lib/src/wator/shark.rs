@@ -1,27 +1,40 @@
-use crate::get_shark_breed_time;
-use crate::get_shark_starve_time;
+use crate::config::{PreyPolicy, Traits};
+use crate::evolution;
+use crate::get_breed_time_of;
+use crate::get_maturity_at;
+use crate::get_prey_policy;
+use crate::get_shark_color;
+use crate::get_starve_time_of;
+use crate::get_traits;
+use crate::is_juvenile;
+use crate::is_past_lifespan;
+use crate::rng::with_rng;
 use crate::Agent;
 use crate::AgentImpl;
 use crate::AgentKind;
 use crate::Cell;
 use crate::Coord;
 use crate::Decision;
-use rand::{thread_rng, Rng};
-use std::convert::TryInto;
+use rand::Rng;
 
 #[derive(Clone, Debug)]
 pub struct Shark {
     pub coordinate: Coord,
     pub breed_count_down: i32,
     pub starve_count_down: i32,
+    pub age: u32,
+    pub traits: Traits,
 }
 
 impl Shark {
     pub fn new(coordinate: Coord) -> Shark {
+        let traits = get_traits(AgentKind::Shark);
         Shark {
             coordinate,
-            breed_count_down: get_shark_breed_time(),
-            starve_count_down: get_shark_starve_time(),
+            breed_count_down: get_maturity_at(AgentKind::Shark, coordinate, &traits),
+            starve_count_down: get_starve_time_of(coordinate, &traits),
+            age: 0,
+            traits,
         }
     }
 }
@@ -29,53 +42,45 @@ impl Shark {
 impl Agent for Shark {
     fn update(&mut self) {
         self.starve_count_down -= 1;
-        self.breed_count_down -= 1;
+        self.breed_count_down = (self.breed_count_down - 1).max(0);
+        self.age = self.age.saturating_add(1);
     }
 
-    fn decide(&self, neighbors: &Vec<Cell>) -> Decision {
-        if self.starve_count_down < 0 {
-            return Decision::Starve(self.coordinate);
+    // The cells do not tell what is around the fish, cornered prey are drawn at random,
+    // and the shark sees no farther than its neighbors whatever its vision
+    fn decide(&self, neighbors: &[Cell]) -> Decision {
+        if is_past_lifespan(AgentKind::Shark, self.age) {
+            return Decision::Die(self.coordinate);
         }
-
-        let from = self.coordinate;
-        let mut rng = thread_rng();
-
-        let fish_in_neighbor = neighbors
+        let free: Vec<Coord> = neighbors
             .iter()
-            .filter(|cell| !cell.is_empty())
-            .collect::<Vec<&Cell>>();
-
-        let decision = if !fish_in_neighbor.is_empty() {
-            let idx = rng.gen_range(0, fish_in_neighbor.len());
-            let to: Result<AgentImpl, String> = neighbors[idx].clone().try_into();
-
-            if let Ok(to) = to {
-                if self.breed_count_down < 0 {
-                    Decision::EatAndBreed(from, to.coordinate())
-                } else {
-                    Decision::EatAndMove(from, to.coordinate())
-                }
-            } else {
-                Decision::Stall(self.coordinate)
-            }
-        } else if !neighbors.is_empty() {
-            let idx = rng.gen_range(0, neighbors.len());
-            let to = neighbors[idx].clone().try_into();
-
-            if let Ok(to) = to {
-                if self.breed_count_down < 0 {
-                    Decision::MoveAndBreed(from, to)
-                } else {
-                    Decision::Move(from, to)
-                }
-            } else {
-                Decision::Stall(self.coordinate)
-            }
+            .filter(|cell| cell.is_empty())
+            .map(Cell::to_coord_unchecked)
+            .collect();
+        let prey: Vec<Prey> = neighbors
+            .iter()
+            .filter_map(|cell| match cell {
+                Cell::Filled(agent) if agent.get_kind() == AgentKind::Fish => Some(Prey {
+                    coord: agent.coordinate(),
+                    age: agent.get_age(),
+                    escapes: 0,
+                }),
+                _ => None,
+            })
+            .collect();
+        let breed_count_down = if is_juvenile(AgentKind::Shark, self.age) {
+            self.breed_count_down.max(1)
         } else {
-            Decision::Stall(self.coordinate)
+            self.breed_count_down
         };
-
-        decision
+        decide(
+            self.coordinate,
+            breed_count_down,
+            self.starve_count_down,
+            &free,
+            &prey,
+            get_prey_policy(),
+        )
     }
 
     fn get_kind(&self) -> crate::AgentKind {
@@ -86,15 +91,30 @@ impl Agent for Shark {
     }
 
     fn breed(&mut self) -> AgentImpl {
-        self.breed_count_down = get_shark_breed_time();
+        self.breed_count_down = get_breed_time_of(AgentKind::Shark, self.coordinate, &self.traits);
+        let traits = evolution::inherit(self.traits);
         Box::new(Shark {
             coordinate: self.coordinate,
-            breed_count_down: get_shark_breed_time(),
-            starve_count_down: get_shark_starve_time(),
+            breed_count_down: get_maturity_at(AgentKind::Shark, self.coordinate, &traits),
+            starve_count_down: get_starve_time_of(self.coordinate, &traits),
+            age: 0,
+            traits,
         })
     }
     fn get_color(&self) -> (f32, f32, f32) {
-        (1.0, 0.0, 0.0)
+        get_shark_color()
+    }
+    fn get_breed_count_down(&self) -> i32 {
+        self.breed_count_down
+    }
+    fn get_starve_count_down(&self) -> i32 {
+        self.starve_count_down
+    }
+    fn get_age(&self) -> u32 {
+        self.age
+    }
+    fn get_traits(&self) -> Traits {
+        self.traits
     }
     fn set_coordinate(&mut self, coord: Coord) {
         self.coordinate = coord
@@ -104,6 +124,67 @@ impl Agent for Shark {
     }
 
     fn reset_starve_count_down(&mut self) {
-        self.starve_count_down = get_shark_starve_time()
+        self.starve_count_down = get_starve_time_of(self.coordinate, &self.traits)
+    }
+}
+
+// A fish next to a shark
+#[derive(Clone, Copy, Debug)]
+pub struct Prey {
+    pub coord: Coord,
+    // Ticks the fish lived
+    pub age: u32,
+    // Free cells around the fish
+    pub escapes: u8,
+}
+
+// A shark eats one of the fish around it, chosen by `policy`, otherwise moves to
+// one of the `free` cells, and breeds once its count down reached 0
+pub fn decide(
+    from: Coord,
+    breed_count_down: i32,
+    starve_count_down: i32,
+    free: &[Coord],
+    prey: &[Prey],
+    policy: PreyPolicy,
+) -> Decision {
+    if starve_count_down < 0 {
+        return Decision::Starve(from);
+    }
+
+    if !prey.is_empty() {
+        let to = choose(prey, policy);
+        if breed_count_down <= 0 {
+            Decision::EatAndBreed(from, to)
+        } else {
+            Decision::EatAndMove(from, to)
+        }
+    } else if !free.is_empty() {
+        let to = free[with_rng(|rng| rng.gen_range(0, free.len()))];
+        if breed_count_down <= 0 {
+            Decision::MoveAndBreed(from, to)
+        } else {
+            Decision::Move(from, to)
+        }
+    } else {
+        Decision::Stall(from)
+    }
+}
+
+// The prey ranked first by `policy`, ties are drawn at random
+fn choose(prey: &[Prey], policy: PreyPolicy) -> Coord {
+    let rank = |prey: &Prey| match policy {
+        PreyPolicy::Random => 0,
+        PreyPolicy::Oldest => -i64::from(prey.age),
+        PreyPolicy::Cornered => i64::from(prey.escapes),
+    };
+    let best = prey.iter().map(rank).min().unwrap_or(0);
+
+    let mut candidates = [Coord(0, 0); 8];
+    let mut count = 0;
+    for prey in prey.iter().filter(|prey| rank(prey) == best) {
+        candidates[count] = prey.coord;
+        count += 1;
     }
+    candidates[with_rng(|rng| rng.gen_range(0, count))]
 }
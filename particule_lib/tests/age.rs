@@ -0,0 +1,163 @@
+mod common;
+
+use common::{lock, populated, sma, walled_in, FED, TIMES};
+use particule_lib::config::Breeding;
+use particule_lib::sma::Sma;
+use particule_lib::trace::AgeStats;
+use particule_lib::AgentKind;
+use particule_lib::Coord;
+use proptest::prelude::*;
+
+const KINDS: [AgentKind; 2] = [AgentKind::Fish, AgentKind::Shark];
+
+fn age(sma: &Sma, coord: Coord) -> u32 {
+    sma.environment.ages[coord.as_idx()]
+}
+
+#[test]
+fn agents_age_once_per_tick() {
+    let _globals = lock();
+    for &kind in KINDS.iter() {
+        let mut sma = walled_in(kind, Breeding::DEFAULT);
+        assert_eq!(age(&sma, Coord(1, 1)), 0);
+        for tick in 1..=20 {
+            sma.tick();
+            assert_eq!(age(&sma, Coord(1, 1)), tick, "{:?}", kind);
+        }
+    }
+}
+
+#[test]
+fn moving_agents_age_once_per_tick() {
+    let _globals = lock();
+    for seed in 0..20 {
+        // Agents moving to cells still to be updated are not updated again, the sharks
+        // placed at the start never die and are as old as the board
+        let mut sma = sma(20, 20, &FED, false, seed);
+        sma.gen_agents(100, 20);
+        for tick in 1..=30 {
+            sma.tick();
+            let oldest = sma.environment.ages.iter().max().copied();
+            assert_eq!(oldest, Some(tick), "seed {}", seed);
+        }
+    }
+}
+
+#[test]
+fn agent_dies_once_it_reached_its_lifespan() {
+    let _globals = lock();
+    for &kind in KINDS.iter() {
+        let mut sma = walled_in(kind, Breeding::DEFAULT);
+        sma.set_lifespans(Some(5), Some(5));
+        for _ in 0..5 {
+            let trace = sma.tick();
+            assert_eq!(trace.fish_death + trace.shark_death, 0);
+        }
+
+        let trace = sma.tick();
+
+        assert_eq!(trace.fish_death + trace.shark_death, 1, "{:?}", kind);
+        assert_eq!(sma.environment.count(kind), 0);
+        assert_eq!(age(&sma, Coord(1, 1)), 0);
+    }
+}
+
+#[test]
+fn young_are_born_at_age_zero() {
+    let _globals = lock();
+    for &kind in KINDS.iter() {
+        let mut sma = walled_in(kind, Breeding::DEFAULT);
+        for _ in 0..20 {
+            sma.tick();
+        }
+
+        // The parent goes to the free cell, its young stays in the middle
+        sma.environment.remove_agent(Coord(0, 0));
+        let trace = sma.tick();
+
+        assert_eq!(trace.fish_birth + trace.shark_birth, 1, "{:?}", kind);
+        assert_eq!(age(&sma, Coord(0, 0)), 21);
+        assert_eq!(age(&sma, Coord(1, 1)), 0);
+    }
+}
+
+#[test]
+fn juveniles_do_not_breed() {
+    let _globals = lock();
+    for &kind in KINDS.iter() {
+        let breeding = Breeding {
+            adult_age: 30,
+            ..Breeding::DEFAULT
+        };
+        let mut sma = walled_in(kind, breeding);
+        for _ in 0..20 {
+            sma.tick();
+        }
+
+        // Ready since long ago but still too young
+        sma.environment.remove_agent(Coord(0, 0));
+        let trace = sma.tick();
+
+        assert_eq!(trace.fish_birth + trace.shark_birth, 0, "{:?}", kind);
+        assert_eq!(sma.environment.count(kind), 1);
+        assert_eq!(sma.environment.breed_count_downs[Coord(0, 0).as_idx()], 0);
+    }
+}
+
+#[test]
+fn age_stats_describe_the_ages() {
+    let _globals = lock();
+    let _sma = sma(3, 3, &TIMES, false, 0);
+
+    let stats = AgeStats::of(AgentKind::Fish, vec![3, 1, 4, 1, 5, 9]);
+
+    assert_eq!(stats.count, 6);
+    assert_eq!(stats.juveniles, 0);
+    assert!((stats.mean - 23.0 / 6.0).abs() < 1e-9);
+    assert_eq!(stats.median, 3);
+    assert_eq!(stats.max, 9);
+    assert_eq!(AgeStats::of(AgentKind::Fish, vec![]), AgeStats::default());
+}
+
+#[test]
+fn stats_give_the_ages_left_on_the_board() {
+    let _globals = lock();
+    let breeding = Breeding {
+        adult_age: 5,
+        ..Breeding::DEFAULT
+    };
+    let mut sma = walled_in(AgentKind::Shark, breeding);
+    for _ in 0..3 {
+        sma.tick();
+    }
+
+    let stats = sma.stats();
+
+    assert_eq!(stats.fish_ages, AgeStats::default());
+    assert_eq!(stats.shark_ages.count, 1);
+    assert_eq!(stats.shark_ages.juveniles, 1);
+    assert_eq!(stats.shark_ages.max, 3);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn no_agent_outlives_its_lifespan(
+        fish_lifespan in 1..20u32,
+        shark_lifespan in 1..20u32,
+        borderless in any::<bool>(),
+        seed in any::<u64>(),
+    ) {
+        let _globals = lock();
+        let mut sma = populated(20, 20, 100, 20, borderless, seed);
+        sma.set_lifespans(Some(fish_lifespan), Some(shark_lifespan));
+
+        for _ in 0..50 {
+            sma.tick();
+            let stats = sma.stats();
+            prop_assert!(stats.fish_ages.max <= fish_lifespan);
+            prop_assert!(stats.shark_ages.max <= shark_lifespan);
+        }
+    }
+}
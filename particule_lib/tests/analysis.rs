@@ -0,0 +1,143 @@
+mod common;
+
+use common::{lock, populated};
+use particule_lib::analysis::{self, Analysis};
+use particule_lib::rng;
+use particule_lib::trace::Populations;
+use proptest::prelude::*;
+use rand::Rng;
+use std::f64::consts::PI;
+
+// Fish and sharks swinging around their means, the sharks `lag` ticks behind
+fn cycles(len: usize, period: f64, lag: f64) -> Populations {
+    let mut populations = Populations::default();
+    for tick in 0..len {
+        let phase = |delay: f64| (2.0 * PI * (tick as f64 - delay) / period).sin();
+        populations.push(
+            (1000.0 + 300.0 * phase(0.0)).round() as u64,
+            (200.0 + 50.0 * phase(lag)).round() as u64,
+        );
+    }
+    populations
+}
+
+fn analyze(fish: &[u64], sharks: &[u64]) -> Analysis {
+    analysis::analyze(&Populations {
+        fish: fish.to_vec(),
+        sharks: sharks.to_vec(),
+    })
+}
+
+#[test]
+fn extinction_is_the_first_tick_without_agents() {
+    let analysis = analyze(&[5, 4, 0, 2, 1, 0, 0, 0], &[3, 0, 0, 0, 0, 0, 0, 0]);
+
+    assert_eq!(analysis.fish.extinction, Some(5));
+    assert_eq!(analysis.sharks.extinction, Some(1));
+    assert_eq!(analysis.fish.oscillation, None);
+    assert_eq!(analysis.lag, None);
+
+    let analysis = analyze(&[0, 0, 0], &[0, 0, 1]);
+    assert_eq!(analysis.fish.extinction, Some(0));
+    assert_eq!(analysis.sharks.extinction, None);
+}
+
+#[test]
+fn cycles_give_their_period_amplitude_and_lag() {
+    let analysis = analysis::analyze(&cycles(1000, 50.0, 12.0));
+
+    assert_eq!(analysis.steady_from, 500);
+    assert!((analysis.fish.mean - 1000.0).abs() < 5.0);
+    assert!((analysis.sharks.mean - 200.0).abs() < 1.0);
+    let fish = analysis.fish.oscillation.unwrap();
+    let sharks = analysis.sharks.oscillation.unwrap();
+    assert!((fish.period - 50.0).abs() < 0.2, "{:?}", fish);
+    assert!((sharks.period - 50.0).abs() < 0.2, "{:?}", sharks);
+    assert!((fish.amplitude - 300.0).abs() < 3.0, "{:?}", fish);
+    assert!((sharks.amplitude - 50.0).abs() < 1.0, "{:?}", sharks);
+    assert!(fish.regularity > 0.99);
+    assert!((analysis.lag.unwrap() - 12.0).abs() < 0.2);
+}
+
+#[test]
+fn steady_populations_do_not_oscillate() {
+    let analysis = analyze(&[100; 200], &[10; 200]);
+
+    assert_eq!(analysis.fish.mean, 100.0);
+    assert_eq!(analysis.fish.oscillation, None);
+    assert_eq!(analysis.sharks.oscillation, None);
+    assert_eq!(analysis.lag, None);
+
+    // Noise around the mean has no period either
+    let noise: Vec<u64> = rng::scoped(3, || {
+        rng::with_rng(|rng| (0..2000).map(|_| rng.gen_range(900, 1100)).collect())
+    });
+    assert_eq!(analyze(&noise, &noise).fish.oscillation, None);
+}
+
+#[test]
+fn too_few_cycles_are_not_measured() {
+    // One and a half periods in the steady state
+    let analysis = analysis::analyze(&cycles(300, 100.0, 0.0));
+
+    assert_eq!(analysis.fish.oscillation, None);
+}
+
+#[test]
+fn summary_has_a_line_per_species_and_the_lag() {
+    let analysis = analysis::analyze(&cycles(1000, 50.0, 12.0));
+    let lines: Vec<String> = analysis.to_string().lines().map(String::from).collect();
+
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("fish: mean 100"));
+    assert!(lines[0].contains("period 50."));
+    assert!(lines[1].starts_with("sharks: mean 200"));
+    assert!(lines[2].starts_with("lag: sharks peak 12."));
+
+    let extinct = analyze(&[5, 6, 7], &[1, 0, 0]).to_string();
+    assert_eq!(
+        extinct,
+        "fish: mean 6.5, no oscillation\nsharks: extinct at tick 1"
+    );
+}
+
+#[test]
+fn simulation_runs_can_be_analyzed() {
+    let _globals = lock();
+    let mut sma = populated(60, 40, 800, 80, true, 5);
+    let mut populations = Populations::default();
+    populations.record(&sma.environment);
+    for _ in 0..300 {
+        sma.tick();
+        populations.record(&sma.environment);
+    }
+
+    let analysis = analysis::analyze(&populations);
+
+    let steady = &populations.fish[150..];
+    let mean = steady.iter().sum::<u64>() as f64 / steady.len() as f64;
+    assert!((analysis.fish.mean - mean).abs() < 1e-9);
+    if let Some(oscillation) = analysis.sharks.oscillation {
+        assert!(oscillation.period > 1.0 && oscillation.period <= 75.0);
+        assert!(oscillation.amplitude > 0.0);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn any_cycle_is_measured(period in 8.0..120.0f64, lag in 0.0..1.0f64) {
+        let lag = lag * (period - 1.0);
+
+        let analysis = analysis::analyze(&cycles(2000, period, lag));
+
+        let fish = analysis.fish.oscillation.unwrap();
+        prop_assert!((fish.period - period).abs() <= 0.02 * period, "{:?}", fish);
+        // Lags a whole period apart are the same
+        let found = analysis.lag.unwrap();
+        let error = (found - lag).rem_euclid(period);
+        let error = error.min(period - error);
+        prop_assert!(error <= 0.5 + 0.02 * period, "{} for {}", found, lag);
+    }
+}
@@ -0,0 +1,295 @@
+mod common;
+
+use common::{count, lock, populated, sma, walled_in, TIMES};
+use particule_lib::config::{Breeding, PreyPolicy, WhenBlocked};
+use particule_lib::rng;
+use particule_lib::sma::Sma;
+use particule_lib::wator::{fish, shark};
+use particule_lib::AgentKind;
+use particule_lib::CellKind;
+use particule_lib::Coord;
+use particule_lib::Decision;
+use proptest::prelude::*;
+
+fn breeds(decision: Decision) -> bool {
+    matches!(
+        decision,
+        Decision::MoveAndBreed(..) | Decision::EatAndBreed(..)
+    )
+}
+
+fn breed_count_down(sma: &Sma, coord: Coord) -> i32 {
+    sma.environment.breed_count_downs[coord.as_idx()]
+}
+
+#[test]
+fn breed_count_down_stops_at_zero() {
+    let _globals = lock();
+    for &kind in [AgentKind::Fish, AgentKind::Shark].iter() {
+        let mut sma = walled_in(kind, Breeding::DEFAULT);
+        for _ in 0..20 {
+            sma.tick();
+        }
+        assert_eq!(breed_count_down(&sma, Coord(1, 1)), 0, "{:?}", kind);
+    }
+}
+
+#[test]
+fn blocked_agent_waits_and_breeds_on_its_next_move() {
+    let _globals = lock();
+    for &kind in [AgentKind::Fish, AgentKind::Shark].iter() {
+        let mut sma = walled_in(kind, Breeding::DEFAULT);
+        for _ in 0..20 {
+            let trace = sma.tick();
+            assert_eq!(trace.fish_birth + trace.shark_birth, 0);
+        }
+
+        // The parent goes to the free cell, its young stays in the middle
+        sma.environment.remove_agent(Coord(0, 0));
+        let trace = sma.tick();
+
+        assert_eq!(trace.fish_birth + trace.shark_birth, 1, "{:?}", kind);
+        assert_eq!(sma.environment.count(kind), 2);
+        let breed_time = match kind {
+            AgentKind::Fish => TIMES.fish_breed,
+            AgentKind::Shark => TIMES.shark_breed,
+        };
+        assert_eq!(breed_count_down(&sma, Coord(0, 0)), breed_time);
+    }
+}
+
+#[test]
+fn blocked_agent_skipping_starts_its_count_down_again() {
+    let _globals = lock();
+    let skip = Breeding {
+        when_blocked: WhenBlocked::Skip,
+        ..Breeding::DEFAULT
+    };
+    let mut sma = walled_in(AgentKind::Fish, skip);
+    // Count downs 4, 3, 2, 1 then 0: ready but blocked on the 5th tick
+    for _ in 0..5 {
+        sma.tick();
+    }
+    assert_eq!(breed_count_down(&sma, Coord(1, 1)), TIMES.fish_breed);
+
+    sma.environment.remove_agent(Coord(0, 0));
+    let trace = sma.tick();
+
+    assert_eq!(trace.fish_birth, 0);
+    assert_eq!(breed_count_down(&sma, Coord(0, 0)), TIMES.fish_breed - 1);
+}
+
+#[test]
+fn blocked_agent_breeding_in_place_stays_put() {
+    let _globals = lock();
+    let in_place = Breeding {
+        when_blocked: WhenBlocked::InPlace,
+        ..Breeding::DEFAULT
+    };
+    for &kind in [AgentKind::Fish, AgentKind::Shark].iter() {
+        let mut sma = walled_in(kind, in_place);
+        for _ in 0..20 {
+            let trace = sma.tick();
+            assert_eq!(trace.fish_birth + trace.shark_birth, 0);
+        }
+
+        // The parent stays in the middle, its young takes the free cell
+        sma.environment.remove_agent(Coord(0, 0));
+        let trace = sma.tick();
+
+        assert_eq!(trace.fish_birth + trace.shark_birth, 1, "{:?}", kind);
+        let breed_time = match kind {
+            AgentKind::Fish => TIMES.fish_breed,
+            AgentKind::Shark => TIMES.shark_breed,
+        };
+        assert_eq!(breed_count_down(&sma, Coord(1, 1)), breed_time);
+        assert_eq!(sma.environment.ages[Coord(1, 1).as_idx()], 21);
+        assert_eq!(sma.environment.kinds[Coord(0, 0).as_idx()], CellKind::from(kind));
+        assert_eq!(sma.environment.ages[Coord(0, 0).as_idx()], 0);
+    }
+}
+
+#[test]
+fn agents_blocked_in_place_move_once_the_rule_changes() {
+    let _globals = lock();
+    let in_place = Breeding {
+        when_blocked: WhenBlocked::InPlace,
+        ..Breeding::DEFAULT
+    };
+    for &kind in [AgentKind::Fish, AgentKind::Shark].iter() {
+        let mut sma = walled_in(kind, in_place);
+        for _ in 0..20 {
+            sma.tick();
+        }
+
+        // Reloading the rules drops the wait to breed in place, the agent is only ready
+        sma.set_breeding(Breeding::DEFAULT, Breeding::DEFAULT);
+        assert_eq!(breed_count_down(&sma, Coord(1, 1)), 0, "{:?}", kind);
+
+        // It moves to the free cell and leaves its young behind
+        sma.environment.remove_agent(Coord(0, 0));
+        let trace = sma.tick();
+
+        assert_eq!(trace.fish_birth + trace.shark_birth, 1, "{:?}", kind);
+        assert_eq!(sma.environment.ages[Coord(0, 0).as_idx()], 21);
+        assert_eq!(sma.environment.kinds[Coord(1, 1).as_idx()], CellKind::from(kind));
+        assert_eq!(sma.environment.ages[Coord(1, 1).as_idx()], 0);
+    }
+}
+
+#[test]
+fn litter_fills_the_free_cells_around_the_parent() {
+    let _globals = lock();
+    for litter in 1..=8 {
+        let mut sma = sma(5, 5, &TIMES, false, litter as u64);
+        let breeding = Breeding {
+            litter,
+            ..Breeding::DEFAULT
+        };
+        sma.set_breeding(breeding, breeding);
+        sma.environment.spawn(AgentKind::Fish, Coord(2, 2));
+        sma.environment.breed_count_downs[Coord(2, 2).as_idx()] = 0;
+
+        let trace = sma.tick();
+
+        assert_eq!(trace.fish_birth, litter as i64);
+        assert_eq!(count(&sma, AgentKind::Fish), litter as i64 + 1);
+    }
+}
+
+#[test]
+fn litter_is_limited_by_the_room_around_the_parent() {
+    let _globals = lock();
+    let mut sma = walled_in(
+        AgentKind::Fish,
+        Breeding {
+            litter: 5,
+            ..Breeding::DEFAULT
+        },
+    );
+    sma.environment.breed_count_downs[Coord(1, 1).as_idx()] = 0;
+    sma.environment.remove_agent(Coord(0, 0));
+    sma.environment.remove_agent(Coord(1, 0));
+
+    let trace = sma.tick();
+
+    // One free cell for the parent, the other one and the middle for the young
+    assert_eq!(trace.fish_birth, 2);
+    assert_eq!(count(&sma, AgentKind::Fish), 3);
+}
+
+#[test]
+fn young_wait_until_maturity_and_parents_the_breed_time() {
+    let _globals = lock();
+    let mut sma = walled_in(
+        AgentKind::Fish,
+        Breeding {
+            maturity: Some(10),
+            ..Breeding::DEFAULT
+        },
+    );
+    sma.environment.breed_count_downs[Coord(1, 1).as_idx()] = 0;
+    // The parent can only go to the first cell of the board
+    sma.environment.remove_agent(Coord(0, 0));
+
+    let trace = sma.tick();
+
+    assert_eq!(trace.fish_birth, 1);
+    assert_eq!(breed_count_down(&sma, Coord(1, 1)), 10);
+    assert_eq!(breed_count_down(&sma, Coord(0, 0)), TIMES.fish_breed);
+}
+
+#[test]
+fn agents_start_with_the_same_count_downs_however_they_are_built() {
+    let _globals = lock();
+    let mut sma = sma(2, 2, &TIMES, false, 0);
+    let breeding = Breeding {
+        maturity: Some(7),
+        ..Breeding::DEFAULT
+    };
+    sma.set_breeding(breeding, breeding);
+    sma.environment.spawn(AgentKind::Fish, Coord(0, 0));
+    sma.environment.spawn(AgentKind::Shark, Coord(1, 0));
+    sma.environment.add_agent(Box::new(fish::Fish::new(Coord(0, 1))));
+    sma.environment.add_agent(Box::new(shark::Shark::new(Coord(1, 1))));
+
+    let count_downs = |coord: Coord| {
+        let idx = coord.as_idx();
+        (sma.environment.breed_count_downs[idx], sma.environment.starve_count_downs[idx])
+    };
+    assert_eq!(count_downs(Coord(0, 0)), (7, 0));
+    assert_eq!(count_downs(Coord(0, 1)), count_downs(Coord(0, 0)));
+    assert_eq!(count_downs(Coord(1, 0)), (7, TIMES.shark_starve));
+    assert_eq!(count_downs(Coord(1, 1)), count_downs(Coord(1, 0)));
+}
+
+#[test]
+fn sharks_breed_by_the_same_rule_when_eating() {
+    let _globals = lock();
+    let breeding = Breeding {
+        litter: 2,
+        ..Breeding::DEFAULT
+    };
+    let mut sma = walled_in(AgentKind::Shark, breeding);
+    sma.environment.breed_count_downs[Coord(1, 1).as_idx()] = 0;
+    // A blocked fish on the first cell and a free cell for the second young
+    sma.environment.remove_agent(Coord(0, 0));
+    sma.environment.spawn(AgentKind::Fish, Coord(0, 0));
+    sma.environment.remove_agent(Coord(2, 2));
+
+    let trace = sma.tick();
+
+    assert_eq!(trace.fish_death, 1);
+    assert_eq!(trace.shark_birth, 2);
+    assert_eq!(count(&sma, AgentKind::Shark), 3);
+}
+
+proptest! {
+    #[test]
+    fn fish_and_sharks_are_ready_at_the_same_count_down(
+        breed_count_down in -3..10i32,
+        seed in any::<u64>(),
+    ) {
+        let from = Coord(1, 1);
+        let free = [Coord(0, 1), Coord(2, 1)];
+        let ready = breed_count_down <= 0;
+
+        let fish = rng::scoped(seed, || fish::decide(from, breed_count_down, &free));
+        let shark = rng::scoped(seed, || {
+            shark::decide(from, breed_count_down, 5, &free, &[], PreyPolicy::Random)
+        });
+
+        prop_assert_eq!(breeds(fish), ready);
+        prop_assert_eq!(breeds(shark), ready);
+    }
+
+    #[test]
+    fn populations_follow_births_and_deaths_with_any_rule(
+        litter in 1..=8u32,
+        maturity in prop::option::of(0..10i32),
+        when_blocked in prop::sample::select(vec![WhenBlocked::Wait, WhenBlocked::Skip, WhenBlocked::InPlace]),
+        borderless in any::<bool>(),
+        seed in any::<u64>(),
+    ) {
+        let _globals = lock();
+        let mut sma = populated(30, 20, 150, 30, borderless, seed);
+        let breeding = Breeding {
+            when_blocked,
+            litter,
+            maturity,
+            adult_age: 0,
+        };
+        sma.set_breeding(breeding, breeding);
+
+        for _ in 0..20 {
+            let fish = count(&sma, AgentKind::Fish);
+            let sharks = count(&sma, AgentKind::Shark);
+            let trace = sma.tick();
+            prop_assert_eq!(count(&sma, AgentKind::Fish), fish + trace.fish_birth - trace.fish_death);
+            prop_assert_eq!(count(&sma, AgentKind::Shark), sharks + trace.shark_birth - trace.shark_death);
+            // Only agents blocked while ready to breed in place count below 0
+            let lowest = if when_blocked == WhenBlocked::InPlace { -1 } else { 0 };
+            prop_assert!(sma.environment.breed_count_downs.iter().all(|&count_down| count_down >= lowest));
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// Board sizes, times and the habitat are globals of the library: the tests of a file
+// that build a simulation take `lock` first so they never run at the same time.
+#![allow(dead_code)]
+
+use particule_lib::config::Breeding;
+use particule_lib::rng;
+use particule_lib::sma::Sma;
+use particule_lib::AgentKind;
+use particule_lib::CellKind;
+use particule_lib::Coord;
+use std::sync::{Mutex, MutexGuard};
+
+static GLOBALS: Mutex<()> = Mutex::new(());
+
+pub fn lock() -> MutexGuard<'static, ()> {
+    // A failed test poisons the lock, the globals are set again by the next one anyway
+    GLOBALS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub struct Times {
+    pub fish_breed: i32,
+    pub shark_breed: i32,
+    pub shark_starve: i32,
+}
+
+pub const TIMES: Times = Times {
+    fish_breed: 4,
+    shark_breed: 10,
+    shark_starve: 4,
+};
+
+// Sharks that never starve in the few ticks of a test
+pub const FED: Times = Times {
+    shark_starve: 1000,
+    ..TIMES
+};
+
+// Empty simulation with uniform times and the generator seeded with `seed`
+pub fn sma(width: i32, height: i32, times: &Times, borderless: bool, seed: u64) -> Sma {
+    rng::seed(seed);
+    Sma::new(
+        width,
+        height,
+        times.fish_breed,
+        times.shark_breed,
+        times.shark_starve,
+        borderless,
+    )
+}
+
+// Simulation with `fish` and `sharks` spread at random
+pub fn populated(
+    width: i32,
+    height: i32,
+    fish: u32,
+    sharks: u32,
+    borderless: bool,
+    seed: u64,
+) -> Sma {
+    let mut sma = sma(width, height, &TIMES, borderless, seed);
+    sma.gen_agents(fish, sharks);
+    sma
+}
+
+// An agent in the middle of a 3 x 3 closed board, walls on every other cell, it never moves
+pub fn walled_in(kind: AgentKind, breeding: Breeding) -> Sma {
+    let mut sma = sma(3, 3, &FED, false, 0);
+    sma.set_breeding(breeding, breeding);
+    (0..9)
+        .filter(|&idx| idx != 4)
+        .for_each(|idx| sma.environment.add_wall(Coord::from_idx(idx)));
+    sma.environment.spawn(kind, Coord(1, 1));
+    sma
+}
+
+pub fn count(sma: &Sma, kind: AgentKind) -> i64 {
+    sma.environment.count(kind) as i64
+}
+
+pub fn walls(sma: &Sma) -> Vec<usize> {
+    (0..sma.environment.size())
+        .filter(|&idx| sma.environment.kinds[idx] == CellKind::Wall)
+        .collect()
+}
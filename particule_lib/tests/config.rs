@@ -0,0 +1,214 @@
+use particule_lib::config::{Config, ConfigError, HabitatLayer, Placement, Shape, Wall};
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+// Config files written for a test, tests run in parallel so each one picks its own name
+fn write(name: &str, text: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("particule_{}_{}", process::id(), name));
+    fs::write(&path, text).unwrap();
+    path
+}
+
+const VALID: &str = "
+x = 20
+y = 10
+borderless = false
+fish_number = 50
+fish_breed_time = 3
+shark_number = 10
+shark_breed_time = 10
+shark_starve_time = 3
+";
+
+fn valid(name: &str) -> Config {
+    Config::from_file(write(name, VALID), None).unwrap()
+}
+
+fn fields(config: &Config) -> Vec<&'static str> {
+    match config.validate() {
+        Err(ConfigError::Invalid(errors)) => errors.iter().map(|error| error.field).collect(),
+        other => panic!("expected validation errors, got {:?}", other),
+    }
+}
+
+#[test]
+fn valid_configs_pass() {
+    assert!(valid("valid.toml").validate().is_ok());
+}
+
+#[test]
+fn every_invalid_field_is_reported() {
+    let mut config = valid("every_invalid_field.toml");
+    config.walls = vec![
+        Wall {
+            x: 18,
+            y: 0,
+            width: 5,
+            height: 1,
+        },
+        Wall {
+            x: -1,
+            y: 2,
+            width: 1,
+            height: 1,
+        },
+    ];
+    config.habitat = vec![HabitatLayer {
+        shape: Shape::Zone {
+            x: 0,
+            y: 5,
+            width: 20,
+            height: 6,
+        },
+        fish_breed: 2.0,
+        shark_breed: 1.0,
+        shark_starve: 1.0,
+    }];
+    config.fish_number = 191;
+    config.fish_breed_time = -1;
+    config.shark_starve_time = -3;
+    config.placement = Placement::Map {
+        path: "no/such/placement.txt".into(),
+    };
+
+    assert_eq!(
+        fields(&config),
+        vec![
+            "fish_number",
+            "walls",
+            "walls",
+            "fish_breed_time",
+            "shark_starve_time",
+            "habitat",
+            "placement",
+        ]
+    );
+}
+
+#[test]
+fn unknown_maps_are_reported_with_the_other_errors() {
+    let mut config = valid("unknown_maps.toml");
+    config.map = Some("no/such/map.txt".into());
+    config.shark_breed_time = -1;
+    config.habitat = vec![HabitatLayer {
+        shape: Shape::Map {
+            path: "no/such/habitat.png".into(),
+        },
+        fish_breed: 1.0,
+        shark_breed: 1.0,
+        shark_starve: -1.0,
+    }];
+
+    match config.validate() {
+        Err(ConfigError::Invalid(errors)) => {
+            let errors = errors
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<String>>();
+            assert_eq!(
+                errors,
+                vec![
+                    "map: no file at no/such/map.txt",
+                    "shark_breed_time: must not be negative, got -1",
+                    "habitat: factors must not be negative, got [1.0, 1.0, -1.0]",
+                    "habitat: no file at no/such/habitat.png",
+                ]
+            );
+        }
+        other => panic!("expected validation errors, got {:?}", other),
+    }
+}
+
+#[test]
+fn populations_are_checked_against_the_whole_board() {
+    let mut config = valid("populations.toml");
+    config.fish_number = 190;
+    config.shark_number = 10;
+    assert!(config.validate().is_ok());
+
+    config.shark_number = 11;
+    config.x = 0.0;
+    assert_eq!(fields(&config), vec!["x", "fish_number"]);
+}
+
+const SCENARIOS: &str = r#"
+default = "Calme"
+
+[scenarios.calme]
+x = 20
+y = 10
+borderless = true
+fish_number = 50
+fish_breed_time = 3
+shark_number = 10
+shark_breed_time = 10
+shark_starve_time = 3
+
+[scenarios.Affames]
+x = 30
+y = 30
+borderless = false
+fish_number = 100
+fish_breed_time = 3
+shark_number = 40
+shark_breed_time = 10
+shark_starve_time = 1
+"#;
+
+#[test]
+fn scenarios_are_picked_by_name_whatever_the_case() {
+    let path = write("by_name.toml", SCENARIOS);
+    for &name in ["affames", "AFFAMES", "Affames"].iter() {
+        let config = Config::from_file(&path, Some(name)).unwrap();
+        assert_eq!((config.x, config.shark_starve_time), (30.0, 1), "{}", name);
+    }
+}
+
+#[test]
+fn the_default_scenario_is_used_when_none_is_asked_for() {
+    let config = Config::from_file(write("default.toml", SCENARIOS), None).unwrap();
+    assert_eq!((config.x, config.borderless), (20.0, true));
+}
+
+#[test]
+fn a_single_scenario_needs_no_default() {
+    let path = write(
+        "single.json",
+        r#"{ "scenarios": { "seul": {
+            "x": 8, "y": 8, "borderless": true, "fish_number": 3,
+            "fish_breed_time": 2, "shark_breed_time": 5, "shark_starve_time": 2
+        } } }"#,
+    );
+    let config = Config::from_file(path, None).unwrap();
+    assert_eq!((config.x, config.fish_number), (8.0, 3));
+}
+
+#[test]
+fn several_scenarios_without_default_ask_for_one() {
+    let text = SCENARIOS.replace("default = \"Calme\"", "");
+    match Config::from_file(write("no_default.toml", &text), None) {
+        Err(ConfigError::NoScenario(available)) => assert_eq!(available, vec!["affames", "calme"]),
+        other => panic!("expected NoScenario, got {:?}", other),
+    }
+}
+
+#[test]
+fn unknown_scenarios_list_the_available_ones() {
+    match Config::from_file(write("unknown.toml", SCENARIOS), Some("Tempete")) {
+        Err(ConfigError::UnknownScenario(name, available)) => {
+            assert_eq!(name, "tempete");
+            assert_eq!(available, vec!["affames", "calme"]);
+        }
+        other => panic!("expected UnknownScenario, got {:?}", other),
+    }
+
+    // A file with a single config has no scenario to pick
+    match Config::from_file(write("no_scenarios.toml", VALID), Some("calme")) {
+        Err(ConfigError::UnknownScenario(name, available)) => {
+            assert_eq!(name, "calme");
+            assert!(available.is_empty());
+        }
+        other => panic!("expected UnknownScenario, got {:?}", other),
+    }
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use common::{lock, sma, TIMES};
+use particule_lib::AgentKind;
+use particule_lib::Coord;
+use proptest::prelude::*;
+
+// A board of `width` x `height` cells and a position on it
+fn board_and_coord() -> impl Strategy<Value = (i32, i32, Coord)> {
+    (1..200i32, 1..200i32).prop_flat_map(|(w, h)| {
+        (
+            Just(w),
+            Just(h),
+            (0..w, 0..h).prop_map(|(x, y)| Coord(x, y)),
+        )
+    })
+}
+
+fn offset() -> impl Strategy<Value = Coord> {
+    (-1..=1i32, -1..=1i32).prop_map(|(x, y)| Coord(x, y))
+}
+
+#[test]
+fn index_starts_at_the_origin_row_by_row() {
+    let _globals = lock();
+    sma(10, 5, &TIMES, false, 0);
+
+    assert_eq!(Coord(0, 0).as_idx(), 0);
+    assert_eq!(Coord(9, 0).as_idx(), 9);
+    assert_eq!(Coord(0, 1).as_idx(), 10);
+    assert_eq!(Coord(9, 4).as_idx(), 49);
+    assert_eq!(Coord::from_idx(49), Coord(9, 4));
+}
+
+#[test]
+fn add_stays_on_the_edge() {
+    let _globals = lock();
+    sma(10, 5, &TIMES, false, 0);
+
+    assert_eq!(Coord(3, 2) + Coord(1, -1), Coord(4, 1));
+    assert_eq!(Coord(0, 2) + Coord(-1, 0), Coord(0, 2));
+    assert_eq!(Coord(9, 4) + Coord(1, 1), Coord(9, 4));
+    // One coordinate off the board is enough to stay
+    assert_eq!(Coord(5, 4) + Coord(1, 1), Coord(5, 4));
+}
+
+#[test]
+fn mul_wraps_around() {
+    let _globals = lock();
+    sma(10, 5, &TIMES, true, 0);
+
+    assert_eq!(Coord(3, 2) * Coord(1, -1), Coord(4, 1));
+    assert_eq!(Coord(0, 2) * Coord(-1, 0), Coord(9, 2));
+    assert_eq!(Coord(9, 4) * Coord(1, 1), Coord(0, 0));
+    assert_eq!(Coord(0, 0) * Coord(-1, -1), Coord(9, 4));
+}
+
+#[test]
+#[allow(deprecated)]
+fn adjacent_cells_are_the_empty_neighbors_of_agents() {
+    let _globals = lock();
+    let mut sma = sma(4, 3, &TIMES, false, 0);
+    sma.environment.spawn(AgentKind::Fish, Coord(0, 0));
+    sma.environment.spawn(AgentKind::Shark, Coord(3, 2));
+    sma.environment.add_wall(Coord(1, 1));
+
+    let mut adjacent = sma.environment.get_adjacent();
+    adjacent.sort_by_key(Coord::as_idx);
+    assert_eq!(
+        adjacent,
+        vec![
+            Coord(1, 0),
+            Coord(0, 1),
+            Coord(2, 1),
+            Coord(3, 1),
+            Coord(2, 2)
+        ]
+    );
+}
+
+proptest! {
+    #[test]
+    fn idx_round_trip((width, height, coord) in board_and_coord()) {
+        let _globals = lock();
+        sma(width, height, &TIMES, false, 0);
+
+        let idx = coord.as_idx();
+        prop_assert!(idx < (width * height) as usize);
+        prop_assert_eq!(Coord::from_idx(idx as i32), coord);
+    }
+
+    #[test]
+    fn every_idx_is_a_distinct_coord(width in 1..100i32, height in 1..100i32) {
+        let _globals = lock();
+        sma(width, height, &TIMES, false, 0);
+
+        for idx in 0..width * height {
+            let coord = Coord::from_idx(idx);
+            prop_assert!(coord.0 >= 0 && coord.0 < width && coord.1 >= 0 && coord.1 < height);
+            prop_assert_eq!(coord.as_idx(), idx as usize);
+        }
+    }
+
+    #[test]
+    fn add_moves_inside_or_stays((width, height, coord) in board_and_coord(), offset in offset()) {
+        let _globals = lock();
+        sma(width, height, &TIMES, false, 0);
+
+        let x = coord.0 + offset.0;
+        let y = coord.1 + offset.1;
+        let expected = if x >= 0 && x < width && y >= 0 && y < height {
+            Coord(x, y)
+        } else {
+            coord
+        };
+        prop_assert_eq!(coord + offset, expected);
+    }
+
+    #[test]
+    fn mul_wraps_on_the_torus((width, height, coord) in board_and_coord(), offset in offset()) {
+        let _globals = lock();
+        sma(width, height, &TIMES, true, 0);
+
+        let expected = Coord(
+            (coord.0 + offset.0).rem_euclid(width),
+            (coord.1 + offset.1).rem_euclid(height),
+        );
+        prop_assert_eq!(coord * offset, expected);
+    }
+
+    #[test]
+    fn mul_is_undone_by_the_opposite_offset(
+        (width, height, coord) in board_and_coord(),
+        offset in offset(),
+    ) {
+        let _globals = lock();
+        sma(width, height, &TIMES, true, 0);
+
+        prop_assert_eq!((coord * offset) * Coord(-offset.0, -offset.1), coord);
+    }
+}
@@ -0,0 +1,226 @@
+mod common;
+
+use common::{lock, sma, FED, TIMES};
+use particule_lib::config::{Evolution, Traits};
+use particule_lib::evolution::{self, MAX_FACTOR, MAX_SPEED, MAX_VISION, MIN_FACTOR};
+use particule_lib::rng;
+use particule_lib::sma::Sma;
+use particule_lib::AgentKind;
+use particule_lib::CellKind;
+use particule_lib::Coord;
+use proptest::prelude::*;
+
+fn traits() -> impl Strategy<Value = Traits> {
+    (
+        MIN_FACTOR..=MAX_FACTOR,
+        MIN_FACTOR..=MAX_FACTOR,
+        0..=MAX_VISION,
+        1..=MAX_SPEED,
+    )
+        .prop_map(|(breed, starve, vision, speed)| Traits {
+            breed,
+            starve,
+            vision,
+            speed,
+        })
+}
+
+fn traits_at(sma: &Sma, coord: Coord) -> Traits {
+    sma.environment.traits[coord.as_idx()]
+}
+
+fn walls(sma: &mut Sma, walls: &[Coord]) {
+    walls
+        .iter()
+        .for_each(|&wall| sma.environment.add_wall(wall));
+}
+
+#[test]
+fn young_inherit_their_parent_traits() {
+    let _globals = lock();
+    let traits = Traits {
+        breed: 2.0,
+        starve: 1.5,
+        vision: 3,
+        speed: 1,
+    };
+    for &kind in [AgentKind::Fish, AgentKind::Shark].iter() {
+        // Walled in the middle of a 3 x 3 board until it is ready
+        let mut sma = sma(3, 3, &FED, false, 0);
+        sma.set_traits(traits, traits);
+        (0..9)
+            .filter(|&idx| idx != 4)
+            .for_each(|idx| sma.environment.add_wall(Coord::from_idx(idx)));
+        sma.environment.spawn(kind, Coord(1, 1));
+        for _ in 0..30 {
+            sma.tick();
+        }
+
+        sma.environment.remove_agent(Coord(0, 0));
+        sma.tick();
+
+        assert_eq!(traits_at(&sma, Coord(0, 0)), traits, "{:?}", kind);
+        assert_eq!(traits_at(&sma, Coord(1, 1)), traits);
+        // The parent waits twice its breed time before breeding again
+        let breed_time = match kind {
+            AgentKind::Fish => TIMES.fish_breed,
+            AgentKind::Shark => TIMES.shark_breed,
+        };
+        let breed_count_down = sma.environment.breed_count_downs[Coord(0, 0).as_idx()];
+        assert_eq!(breed_count_down, 2 * breed_time);
+    }
+}
+
+#[test]
+fn shark_heads_to_the_fish_it_sees() {
+    let _globals = lock();
+    for seed in 0..20 {
+        // The fish is walled in a corner, the shark in the opposite one sees it
+        let mut sma = sma(5, 5, &FED, false, seed);
+        let shark = Traits {
+            vision: 4,
+            ..Traits::DEFAULT
+        };
+        sma.set_traits(Traits::DEFAULT, shark);
+        walls(&mut sma, &[Coord(1, 0), Coord(0, 1), Coord(1, 1)]);
+        sma.environment.spawn(AgentKind::Fish, Coord(0, 0));
+        sma.environment.spawn(AgentKind::Shark, Coord(4, 4));
+
+        sma.tick();
+
+        assert!(sma.environment.is_shark_at(Coord(3, 3)), "seed {}", seed);
+    }
+}
+
+#[test]
+fn fish_flees_the_sharks_it_sees() {
+    let _globals = lock();
+    for seed in 0..20 {
+        // The shark is walled in a corner, the fish in the middle sees it
+        let mut sma = sma(5, 5, &FED, false, seed);
+        let fish = Traits {
+            vision: 3,
+            ..Traits::DEFAULT
+        };
+        sma.set_traits(fish, Traits::DEFAULT);
+        walls(&mut sma, &[Coord(3, 4), Coord(4, 3), Coord(3, 3)]);
+        sma.environment.spawn(AgentKind::Shark, Coord(4, 4));
+        sma.environment.spawn(AgentKind::Fish, Coord(2, 2));
+
+        sma.tick();
+
+        // Never on the two free cells next to the walls around the shark
+        let fish = (0..25)
+            .map(Coord::from_idx)
+            .find(|&coord| sma.environment.kinds[coord.as_idx()] == CellKind::Fish)
+            .unwrap();
+        assert!(
+            fish.0 <= 1 || fish.1 <= 1,
+            "seed {} ends on {:?}",
+            seed,
+            fish
+        );
+    }
+}
+
+#[test]
+fn fast_agents_move_several_cells() {
+    let _globals = lock();
+    let mut farthest = 6;
+    for seed in 0..20 {
+        // On a single row, 3 moves from the end reach 3 or 5
+        let mut sma = sma(7, 1, &FED, false, seed);
+        let fast = Traits {
+            speed: 3,
+            ..Traits::DEFAULT
+        };
+        sma.set_traits(fast, fast);
+        sma.environment.spawn(AgentKind::Fish, Coord(6, 0));
+
+        sma.tick();
+
+        let at = (0..7)
+            .find(|&x| sma.environment.kinds[Coord(x, 0).as_idx()] == CellKind::Fish)
+            .unwrap();
+        assert!(at == 3 || at == 5, "seed {} ends on {}", seed, at);
+        farthest = farthest.min(at);
+    }
+    assert_eq!(farthest, 3);
+}
+
+#[test]
+fn each_extra_cell_costs_a_shark_a_tick_of_starvation() {
+    let _globals = lock();
+    let mut sma = sma(7, 1, &FED, false, 0);
+    let fast = Traits {
+        speed: 3,
+        ..Traits::DEFAULT
+    };
+    sma.set_traits(fast, fast);
+    sma.environment.spawn(AgentKind::Shark, Coord(6, 0));
+
+    sma.tick();
+
+    let shark = (0..7)
+        .map(|x| Coord(x, 0))
+        .find(|&coord| sma.environment.is_shark_at(coord));
+    let starve_count_down = sma.environment.starve_count_downs[shark.unwrap().as_idx()];
+    assert_eq!(starve_count_down, FED.shark_starve - 3);
+}
+
+#[test]
+fn stats_give_the_traits_when_agents_evolve() {
+    let _globals = lock();
+    let mut sma = sma(30, 20, &TIMES, true, 1);
+    sma.gen_agents(150, 20);
+    sma.tick();
+    assert!(sma.stats().fish_traits.is_none());
+
+    sma.set_evolution(Some(Evolution {
+        rate: 1.0,
+        size: 0.5,
+    }));
+    for _ in 0..11 {
+        sma.tick();
+    }
+
+    let stats = sma.stats();
+
+    let fish = stats.fish_traits.unwrap();
+    assert_eq!(fish.count, sma.environment.count(AgentKind::Fish) as u64);
+    assert!(fish.breed.std_dev > 0.0);
+    assert!(fish.breed.min < 1.0 && fish.breed.max > 1.0);
+    assert!(stats.shark_traits.is_some());
+}
+
+proptest! {
+    #[test]
+    fn mutations_stay_within_bounds(
+        traits in traits(),
+        rate in 0.0..=1.0f32,
+        size in 0.0..1.0f32,
+        seed in any::<u64>(),
+    ) {
+        let evolution = Evolution { rate, size };
+
+        let young = rng::scoped(seed, || evolution::mutate(traits, &evolution));
+
+        prop_assert!((MIN_FACTOR..=MAX_FACTOR).contains(&young.breed));
+        prop_assert!((MIN_FACTOR..=MAX_FACTOR).contains(&young.starve));
+        prop_assert!(young.vision <= MAX_VISION);
+        prop_assert!((1..=MAX_SPEED).contains(&young.speed));
+        prop_assert!((young.vision as i32 - traits.vision as i32).abs() <= 1);
+        prop_assert!((young.speed as i32 - traits.speed as i32).abs() <= 1);
+        prop_assert!(young.breed >= (traits.breed * (1.0 - size)).max(MIN_FACTOR) - 1e-4);
+        prop_assert!(young.breed <= (traits.breed * (1.0 + size)).min(MAX_FACTOR) + 1e-4);
+    }
+
+    #[test]
+    fn no_mutation_copies_the_traits(traits in traits(), size in 0.0..1.0f32, seed in any::<u64>()) {
+        let evolution = Evolution { rate: 0.0, size };
+
+        let young = rng::scoped(seed, || evolution::mutate(traits, &evolution));
+
+        prop_assert_eq!(young, traits);
+    }
+}
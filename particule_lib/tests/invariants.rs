@@ -0,0 +1,62 @@
+#![cfg(feature = "invariants")]
+
+mod common;
+
+use common::{lock, populated, sma, TIMES};
+use particule_lib::config::{Evolution, Traits};
+use particule_lib::AgentKind;
+use particule_lib::Coord;
+
+#[test]
+fn consistent_runs_pass_the_checks() {
+    let _globals = lock();
+    let mut sma = populated(50, 40, 600, 80, true, 7);
+    for _ in 0..50 {
+        sma.tick();
+    }
+    assert_eq!(sma.environment.ticks, 50);
+}
+
+#[test]
+fn evolving_runs_pass_the_checks() {
+    let _globals = lock();
+    for &borderless in [false, true].iter() {
+        let mut sma = sma(50, 40, &TIMES, borderless, 7);
+        let traits = Traits {
+            vision: 2,
+            speed: 2,
+            ..Traits::DEFAULT
+        };
+        sma.set_traits(traits, traits);
+        sma.set_evolution(Some(Evolution {
+            rate: 0.5,
+            size: 0.3,
+        }));
+        sma.gen_agents(600, 80);
+        for _ in 0..50 {
+            sma.tick();
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "tick 3 broke the board invariants")]
+fn count_downs_left_on_an_empty_cell_are_reported() {
+    let _globals = lock();
+    let mut sma = sma(10, 10, &TIMES, false, 0);
+    sma.environment.spawn(AgentKind::Fish, Coord(5, 5));
+    sma.tick();
+    sma.tick();
+    sma.environment.breed_count_downs[Coord(0, 0).as_idx()] = 3;
+    sma.tick();
+}
+
+#[test]
+#[should_panic(expected = "101 ages for 100 cells")]
+fn fields_out_of_step_with_the_kinds_are_reported() {
+    let _globals = lock();
+    let mut sma = sma(10, 10, &TIMES, false, 0);
+    sma.environment.spawn(AgentKind::Fish, Coord(5, 5));
+    sma.environment.ages.push(0);
+    sma.tick();
+}
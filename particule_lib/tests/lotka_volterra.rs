@@ -0,0 +1,158 @@
+mod common;
+
+use common::{lock, populated};
+use particule_lib::lotka_volterra::{self, Parameters};
+use particule_lib::trace::Populations;
+use particule_lib::AgentKind;
+use proptest::prelude::*;
+
+const PARAMETERS: Parameters = Parameters {
+    alpha: 0.08,
+    beta: 0.0004,
+    gamma: 0.05,
+    delta: 0.00005,
+};
+
+// Populations following the equations, rounded to whole agents
+fn solution(parameters: &Parameters, start: (f64, f64), len: usize) -> Populations {
+    let mut populations = Populations::default();
+    for (fish, sharks) in parameters.integrate(start, len) {
+        populations.push(fish.round() as u64, sharks.round() as u64);
+    }
+    populations
+}
+
+// Quantity every solution of the equations keeps
+fn first_integral(parameters: &Parameters, (fish, sharks): (f64, f64)) -> f64 {
+    parameters.delta * fish - parameters.gamma * fish.ln() + parameters.beta * sharks
+        - parameters.alpha * sharks.ln()
+}
+
+fn close(value: f64, expected: f64, tolerance: f64) -> bool {
+    (value - expected).abs() <= tolerance * expected.abs()
+}
+
+#[test]
+fn equilibrium_does_not_move() {
+    let (fish, sharks) = PARAMETERS.equilibrium();
+    assert!(close(fish, 1000.0, 1e-12) && close(sharks, 200.0, 1e-12));
+
+    for (model_fish, model_sharks) in PARAMETERS.integrate((fish, sharks), 100) {
+        assert!(close(model_fish, fish, 1e-9) && close(model_sharks, sharks, 1e-9));
+    }
+}
+
+#[test]
+fn fit_finds_the_parameters_of_a_solution() {
+    let populations = solution(&PARAMETERS, (1500.0, 150.0), 400);
+
+    let fit = lotka_volterra::fit(&populations).unwrap();
+
+    let parameters = fit.parameters;
+    assert!(close(parameters.alpha, PARAMETERS.alpha, 0.02), "{}", fit);
+    assert!(close(parameters.beta, PARAMETERS.beta, 0.02), "{}", fit);
+    assert!(close(parameters.gamma, PARAMETERS.gamma, 0.02), "{}", fit);
+    assert!(close(parameters.delta, PARAMETERS.delta, 0.02), "{}", fit);
+    assert!(close(fit.start.0, 1500.0, 0.01) && close(fit.start.1, 150.0, 0.01));
+}
+
+#[test]
+fn comparison_aligns_both_series() {
+    let populations = solution(&PARAMETERS, (1500.0, 150.0), 300);
+
+    let comparison = lotka_volterra::compare(&populations).unwrap();
+
+    assert_eq!(comparison.rows.len(), populations.len());
+    for (tick, row) in comparison.rows.iter().enumerate() {
+        assert_eq!(row.tick, tick as u64);
+        assert_eq!(
+            (row.fish, row.sharks),
+            (populations.fish[tick], populations.sharks[tick])
+        );
+    }
+    assert!(close(comparison.rows[0].model_fish, 1500.0, 0.01));
+    assert!(comparison.fish.r_squared > 0.99 && comparison.sharks.r_squared > 0.99);
+    assert!(comparison.fish.rmse < 5.0 && comparison.sharks.rmse < 5.0);
+
+    let mut csv = Vec::new();
+    comparison.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("tick,fish,sharks,model_fish,model_sharks")
+    );
+    assert!(lines.next().unwrap().starts_with("0,1500,150,"));
+    assert_eq!(lines.count(), populations.len() - 1);
+}
+
+#[test]
+fn nothing_to_fit_without_both_species() {
+    let mut populations = Populations::default();
+    populations.push(100, 10);
+    populations.push(110, 9);
+    assert_eq!(lotka_volterra::fit(&populations), None);
+
+    populations.push(120, 8);
+    assert!(lotka_volterra::fit(&populations).is_some());
+
+    let mut no_sharks = Populations::default();
+    (0..10).for_each(|tick| no_sharks.push(100 + tick, 0));
+    assert_eq!(lotka_volterra::compare(&no_sharks), None);
+}
+
+#[test]
+fn simulation_runs_can_be_compared() {
+    let _globals = lock();
+    let mut sma = populated(60, 40, 800, 80, true, 5);
+    let mut populations = Populations::default();
+    populations.record(&sma.environment);
+    for _ in 0..150 {
+        sma.tick();
+        populations.record(&sma.environment);
+    }
+    assert_eq!(populations.len(), 151);
+    assert_eq!(
+        populations.fish.last().copied(),
+        Some(sma.environment.count(AgentKind::Fish) as u64)
+    );
+
+    let comparison = lotka_volterra::compare(&populations).unwrap();
+
+    let parameters = comparison.fit.parameters;
+    let rates = [
+        parameters.alpha,
+        parameters.beta,
+        parameters.gamma,
+        parameters.delta,
+    ];
+    assert!(rates.iter().all(|rate| rate.is_finite() && *rate > 0.0));
+    assert!(comparison.fish.rmse.is_finite() && comparison.sharks.rmse.is_finite());
+    // Starting from the mean populations already does as well as the mean
+    assert!(comparison.fish.r_squared > -0.1 && comparison.sharks.r_squared > -0.1);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn integration_keeps_the_first_integral(
+        alpha in 0.01..0.2f64,
+        beta in 0.0001..0.01f64,
+        gamma in 0.01..0.2f64,
+        delta in 0.0001..0.01f64,
+        fish in 0.5..2.0f64,
+        sharks in 0.5..2.0f64,
+    ) {
+        let parameters = Parameters { alpha, beta, gamma, delta };
+        let (fish_at_rest, sharks_at_rest) = parameters.equilibrium();
+        let start = (fish * fish_at_rest, sharks * sharks_at_rest);
+        let expected = first_integral(&parameters, start);
+
+        for state in parameters.integrate(start, 300) {
+            prop_assert!(state.0 > 0.0 && state.1 > 0.0);
+            let drift = first_integral(&parameters, state) - expected;
+            prop_assert!(drift.abs() <= 1e-6 * (1.0 + expected.abs()), "drift {}", drift);
+        }
+    }
+}
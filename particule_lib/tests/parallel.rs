@@ -0,0 +1,100 @@
+#![cfg(feature = "parallel")]
+
+mod common;
+
+use common::{count, lock, populated};
+use particule_lib::config::{Evolution, Traits};
+use particule_lib::environment::Environment;
+use particule_lib::sma::Sma;
+use particule_lib::trace::Populations;
+use particule_lib::AgentKind;
+
+// Large enough for the board to be cut in tiles rather than run `update_all`
+const WIDTH: i32 = 60;
+const HEIGHT: i32 = 40;
+const TICKS: usize = 40;
+
+// Agents that see and move far, so tiles reach into each other's cells
+fn evolving(borderless: bool, seed: u64) -> Sma {
+    let mut sma = populated(WIDTH, HEIGHT, 0, 0, borderless, seed);
+    let traits = Traits {
+        vision: 2,
+        speed: 2,
+        ..Traits::DEFAULT
+    };
+    sma.set_traits(traits, traits);
+    sma.set_evolution(Some(Evolution {
+        rate: 0.3,
+        size: 0.2,
+    }));
+    sma.gen_agents(700, 90);
+    sma
+}
+
+fn run(borderless: bool, seed: u64) -> (Environment, Populations) {
+    let mut sma = evolving(borderless, seed);
+    let mut populations = Populations::default();
+    populations.record(&sma.environment);
+    for _ in 0..TICKS {
+        sma.tick();
+        populations.record(&sma.environment);
+    }
+    (sma.environment, populations)
+}
+
+#[test]
+fn boards_this_large_are_cut_in_tiles() {
+    let _globals = lock();
+    // Tiles draw from their own generators, a run of the whole board in order differs
+    let mut tiled = evolving(false, 1);
+    for _ in 0..5 {
+        tiled.environment.update_all_parallel();
+    }
+    let mut whole = evolving(false, 1);
+    for _ in 0..5 {
+        whole.environment.update_all();
+    }
+
+    assert_ne!(tiled.environment.kinds, whole.environment.kinds);
+}
+
+#[test]
+fn same_seed_gives_the_same_run() {
+    let _globals = lock();
+    for &borderless in [false, true].iter() {
+        for seed in 0..5 {
+            let (first, first_populations) = run(borderless, seed);
+            let (second, second_populations) = run(borderless, seed);
+
+            assert_eq!(first.kinds, second.kinds, "seed {}", seed);
+            assert_eq!(first.breed_count_downs, second.breed_count_downs);
+            assert_eq!(first.starve_count_downs, second.starve_count_downs);
+            assert_eq!(first.ages, second.ages);
+            assert_eq!(first.traits, second.traits);
+            assert_eq!(first_populations, second_populations);
+        }
+    }
+}
+
+#[test]
+fn populations_only_change_through_births_and_deaths() {
+    let _globals = lock();
+    for &borderless in [false, true].iter() {
+        for seed in 0..5 {
+            let mut sma = evolving(borderless, seed);
+            for _ in 0..TICKS {
+                let fish = count(&sma, AgentKind::Fish);
+                let sharks = count(&sma, AgentKind::Shark);
+                let trace = sma.tick();
+                assert_eq!(
+                    count(&sma, AgentKind::Fish),
+                    fish + trace.fish_birth - trace.fish_death
+                );
+                assert_eq!(
+                    count(&sma, AgentKind::Shark),
+                    sharks + trace.shark_birth - trace.shark_death
+                );
+            }
+        }
+    }
+}
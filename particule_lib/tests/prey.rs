@@ -0,0 +1,163 @@
+mod common;
+
+use common::{lock, sma, TIMES};
+use particule_lib::config::PreyPolicy;
+use particule_lib::rng;
+use particule_lib::sma::Sma;
+use particule_lib::wator::shark::{self, Prey};
+use particule_lib::AgentKind;
+use particule_lib::CellKind;
+use particule_lib::Coord;
+use particule_lib::Decision;
+use proptest::prelude::*;
+
+const POLICIES: [PreyPolicy; 3] = [PreyPolicy::Random, PreyPolicy::Oldest, PreyPolicy::Cornered];
+
+fn policy() -> impl Strategy<Value = PreyPolicy> {
+    prop::sample::select(POLICIES.to_vec())
+}
+
+// The 8 cells around (1, 1)
+fn around() -> Vec<Coord> {
+    (0..3)
+        .flat_map(|y| (0..3).map(move |x| Coord(x, y)))
+        .filter(|&coord| coord != Coord(1, 1))
+        .collect()
+}
+
+fn eaten(decision: Decision) -> Option<Coord> {
+    match decision {
+        Decision::EatAndMove(_, to) | Decision::EatAndBreed(_, to) => Some(to),
+        _ => None,
+    }
+}
+
+// The fish marked with a breed count down of 100 is the only one that may survive
+fn only_marked_survives(sma: &Sma) -> bool {
+    (0..sma.environment.size())
+        .filter(|&idx| sma.environment.kinds[idx] == CellKind::Fish)
+        .all(|idx| sma.environment.breed_count_downs[idx] > 90)
+}
+
+// A 3 x 3 closed board with a shark in the middle
+fn shark_in_the_middle(policy: PreyPolicy, seed: u64) -> Sma {
+    let mut sma = sma(3, 3, &TIMES, false, seed);
+    sma.set_prey_policy(policy);
+    sma.environment.spawn(AgentKind::Shark, Coord(1, 1));
+    sma
+}
+
+#[test]
+fn shark_eats_the_fish_updated_after_it() {
+    let _globals = lock();
+    for &policy in POLICIES.iter() {
+        for seed in 0..50 {
+            // The fish comes later in the update order, it is still there when the shark decides
+            let mut sma = shark_in_the_middle(policy, seed);
+            sma.environment.spawn(AgentKind::Fish, Coord(2, 2));
+
+            let trace = sma.tick();
+
+            assert_eq!(trace.fish_death, 1, "{:?} with seed {}", policy, seed);
+            assert_eq!(sma.environment.count(AgentKind::Fish), 0);
+        }
+    }
+}
+
+#[test]
+fn cornered_shark_eats_the_fish_with_fewest_escapes() {
+    let _globals = lock();
+    for seed in 0..50 {
+        // On a 5 x 5 board, walls on the right of the fish on (2, 2) leave it 3 free
+        // cells while the fish on (0, 2) against the edge has 4
+        let mut sma = sma(5, 5, &TIMES, false, seed);
+        sma.set_prey_policy(PreyPolicy::Cornered);
+        [Coord(3, 1), Coord(3, 2), Coord(3, 3), Coord(2, 3)]
+            .iter()
+            .for_each(|&wall| sma.environment.add_wall(wall));
+        sma.environment.spawn(AgentKind::Shark, Coord(1, 1));
+        sma.environment.spawn(AgentKind::Fish, Coord(0, 2));
+        sma.environment.spawn(AgentKind::Fish, Coord(2, 2));
+        sma.environment.breed_count_downs[Coord(0, 2).as_idx()] = 100;
+
+        sma.tick();
+
+        assert!(only_marked_survives(&sma), "seed {}", seed);
+    }
+}
+
+#[test]
+fn oldest_shark_eats_the_oldest_fish() {
+    let _globals = lock();
+    for seed in 0..50 {
+        // The younger fish is also the one closest to breeding, it is the marked one
+        let mut sma = shark_in_the_middle(PreyPolicy::Oldest, seed);
+        sma.environment.spawn(AgentKind::Fish, Coord(0, 2));
+        sma.environment.spawn(AgentKind::Fish, Coord(2, 2));
+        sma.environment.ages[Coord(0, 2).as_idx()] = 50;
+        sma.environment.ages[Coord(2, 2).as_idx()] = 10;
+        sma.environment.breed_count_downs[Coord(0, 2).as_idx()] = 200;
+        sma.environment.breed_count_downs[Coord(2, 2).as_idx()] = 100;
+
+        sma.tick();
+
+        assert!(only_marked_survives(&sma), "seed {}", seed);
+    }
+}
+
+proptest! {
+    #[test]
+    fn shark_next_to_a_fish_always_eats(
+        fish in prop::collection::vec(any::<bool>(), 8).prop_filter("no fish", |fish| fish.contains(&true)),
+        ages in prop::collection::vec(0..10u32, 8),
+        escapes in prop::collection::vec(0..8u8, 8),
+        policy in policy(),
+        seed in any::<u64>(),
+    ) {
+        let around = around();
+        let prey: Vec<Prey> = (0..8)
+            .filter(|&i| fish[i])
+            .map(|i| Prey { coord: around[i], age: ages[i], escapes: escapes[i] })
+            .collect();
+        let free: Vec<Coord> = (0..8).filter(|&i| !fish[i]).map(|i| around[i]).collect();
+
+        let decision = rng::scoped(seed, || shark::decide(Coord(1, 1), 3, 3, &free, &prey, policy));
+
+        let to = eaten(decision);
+        prop_assert!(to.is_some(), "{:?} did not eat", decision);
+        prop_assert!(prey.iter().any(|prey| Some(prey.coord) == to));
+    }
+
+    #[test]
+    fn policies_pick_their_best_prey(
+        ages in prop::collection::vec(0..10u32, 1..=8),
+        escapes in prop::collection::vec(0..8u8, 8),
+        seed in any::<u64>(),
+    ) {
+        let around = around();
+        let prey: Vec<Prey> = ages
+            .iter()
+            .enumerate()
+            .map(|(i, &age)| Prey { coord: around[i], age, escapes: escapes[i] })
+            .collect();
+        let pick = |policy| {
+            let decision = rng::scoped(seed, || shark::decide(Coord(1, 1), 3, 3, &[], &prey, policy));
+            let to = eaten(decision).unwrap();
+            *prey.iter().find(|prey| prey.coord == to).unwrap()
+        };
+
+        let oldest = prey.iter().map(|prey| prey.age).max().unwrap();
+        prop_assert_eq!(pick(PreyPolicy::Oldest).age, oldest);
+        let cornered = prey.iter().map(|prey| prey.escapes).min().unwrap();
+        prop_assert_eq!(pick(PreyPolicy::Cornered).escapes, cornered);
+    }
+
+    #[test]
+    fn starving_shark_does_not_eat(policy in policy(), seed in any::<u64>()) {
+        let prey = [Prey { coord: Coord(2, 2), age: 0, escapes: 0 }];
+
+        let decision = rng::scoped(seed, || shark::decide(Coord(1, 1), 3, -1, &[], &prey, policy));
+
+        prop_assert!(matches!(decision, Decision::Starve(Coord(1, 1))));
+    }
+}
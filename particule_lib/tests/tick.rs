@@ -0,0 +1,230 @@
+mod common;
+
+use common::{count, lock, populated, sma, walls, Times, TIMES};
+use particule_lib::config::{HabitatLayer, Shape};
+use particule_lib::sma::Sma;
+use particule_lib::AgentKind;
+use particule_lib::CellKind;
+use particule_lib::Coord;
+use proptest::prelude::*;
+
+// Populated board: size, fish, sharks, topology and seed
+fn board() -> impl Strategy<Value = (i32, i32, u32, u32, bool, u64)> {
+    (2..40i32, 2..40i32, any::<bool>(), any::<u64>()).prop_flat_map(
+        |(width, height, borderless, seed)| {
+            let size = (width * height) as u32;
+            (0..=size).prop_flat_map(move |agents| {
+                (0..=agents)
+                    .prop_map(move |fish| (width, height, fish, agents - fish, borderless, seed))
+            })
+        },
+    )
+}
+
+// Every agent of the board seen as a `Cell` is where its index says
+fn assert_coordinates(sma: &Sma) -> Result<(), TestCaseError> {
+    for idx in 0..sma.environment.size() {
+        let coord = Coord::from_idx(idx as i32);
+        let cell = sma.environment.cell(coord);
+        prop_assert_eq!(cell.to_coord_unchecked(), coord);
+        prop_assert_eq!(cell.to_coord_unchecked().as_idx(), idx);
+    }
+    Ok(())
+}
+
+#[test]
+fn spawned_agents_are_where_they_were_put() {
+    let _globals = lock();
+    let mut sma = sma(8, 6, &TIMES, false, 0);
+    sma.environment.spawn(AgentKind::Fish, Coord(3, 2));
+    sma.environment.spawn(AgentKind::Shark, Coord(7, 5));
+    // An occupied cell keeps its agent
+    sma.environment.spawn(AgentKind::Shark, Coord(3, 2));
+
+    assert_eq!(sma.environment.kinds[Coord(3, 2).as_idx()], CellKind::Fish);
+    assert_eq!(sma.environment.kinds[Coord(7, 5).as_idx()], CellKind::Shark);
+    assert_eq!(sma.environment.count(AgentKind::Fish), 1);
+    assert_eq!(sma.environment.count(AgentKind::Shark), 1);
+    assert_eq!(
+        sma.environment.cell(Coord(7, 5)).to_coord_unchecked(),
+        Coord(7, 5)
+    );
+}
+
+// A shark alone in a closed 1 x 1 board can neither move nor eat
+fn lone_shark(shark_starve: i32) -> Sma {
+    let times = Times {
+        shark_starve,
+        ..TIMES
+    };
+    let mut sma = sma(1, 1, &times, false, 0);
+    sma.environment.spawn(AgentKind::Shark, Coord(0, 0));
+    sma
+}
+
+#[test]
+fn shark_starves_once_its_count_down_is_below_zero() {
+    let _globals = lock();
+    let mut sma = lone_shark(3);
+
+    // Count downs 3, 2, 1, 0 then -1: the shark lives 4 ticks and starves on the 5th
+    for tick in 1..=4 {
+        let trace = sma.tick();
+        assert_eq!(trace.shark_death, 0, "tick {}", tick);
+        assert_eq!(sma.environment.starve_count_downs[0], 3 - tick);
+    }
+    let trace = sma.tick();
+    assert_eq!(trace.shark_death, 1);
+    assert_eq!(sma.environment.kinds[0], CellKind::Empty);
+}
+
+#[test]
+fn eating_resets_the_starve_count_down() {
+    let _globals = lock();
+    let mut sma = sma(2, 1, &TIMES, false, 0);
+    // The shark comes second in the update order, it is not updated again after eating
+    sma.environment.spawn(AgentKind::Fish, Coord(0, 0));
+    sma.environment.spawn(AgentKind::Shark, Coord(1, 0));
+    sma.environment.starve_count_downs[1] = 0;
+
+    let trace = sma.tick();
+
+    assert_eq!(trace.fish_death, 1);
+    assert_eq!(trace.shark_death, 0);
+    assert_eq!(sma.environment.kinds[0], CellKind::Shark);
+    assert_eq!(sma.environment.starve_count_downs[0], TIMES.shark_starve);
+}
+
+#[test]
+fn new_simulations_leave_the_previous_habitat() {
+    let _globals = lock();
+    let mut small = sma(3, 3, &TIMES, false, 0);
+    let layer = HabitatLayer {
+        shape: Shape::Everywhere,
+        fish_breed: 2.0,
+        shark_breed: 1.0,
+        shark_starve: 1.0,
+    };
+    small.set_habitat(&[layer]).unwrap();
+
+    // The 3 x 3 habitat would be read past its end on a larger board
+    let mut sma = sma(20, 20, &TIMES, false, 0);
+    sma.environment.spawn(AgentKind::Fish, Coord(19, 19));
+
+    assert_eq!(
+        sma.environment.breed_count_downs[Coord(19, 19).as_idx()],
+        TIMES.fish_breed
+    );
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn agents_stay_on_their_index((width, height, fish, sharks, borderless, seed) in board()) {
+        let _globals = lock();
+        let mut sma = populated(width, height, fish, sharks, borderless, seed);
+
+        for _ in 0..10 {
+            assert_coordinates(&sma)?;
+            sma.tick();
+        }
+        assert_coordinates(&sma)?;
+    }
+
+    #[test]
+    fn population_changes_by_traced_births_and_deaths(
+        (width, height, fish, sharks, borderless, seed) in board(),
+    ) {
+        let _globals = lock();
+        let mut sma = populated(width, height, fish, sharks, borderless, seed);
+
+        for _ in 0..20 {
+            let fish = count(&sma, AgentKind::Fish);
+            let sharks = count(&sma, AgentKind::Shark);
+            let trace = sma.tick();
+            prop_assert_eq!(
+                count(&sma, AgentKind::Fish),
+                fish + trace.fish_birth - trace.fish_death
+            );
+            prop_assert_eq!(
+                count(&sma, AgentKind::Shark),
+                sharks + trace.shark_birth - trace.shark_death
+            );
+        }
+    }
+
+    // Only a shark eating a fish takes an occupied cell: with no shark no fish is ever lost,
+    // and every fish death is paid by a shark that moved
+    #[test]
+    fn moves_never_overwrite_an_agent((width, height, fish, borderless, seed) in
+        (2..40i32, 2..40i32, any::<bool>(), any::<u64>()).prop_flat_map(|(w, h, b, s)| {
+            (Just(w), Just(h), 0..=(w * h) as u32, Just(b), Just(s))
+        })
+    ) {
+        let _globals = lock();
+        let mut sma = populated(width, height, fish, 0, borderless, seed);
+
+        for _ in 0..20 {
+            let before = count(&sma, AgentKind::Fish);
+            let trace = sma.tick();
+            prop_assert_eq!(trace.fish_death, 0);
+            prop_assert_eq!(count(&sma, AgentKind::Fish), before + trace.fish_birth);
+        }
+    }
+
+    // Long starve times keep the sharks alive whatever their path
+    #[test]
+    fn sharks_never_land_on_sharks(
+        (width, height, sharks, borderless, seed) in
+        (2..40i32, 2..40i32, any::<bool>(), any::<u64>()).prop_flat_map(|(w, h, b, s)| {
+            (Just(w), Just(h), 0..=(w * h) as u32, Just(b), Just(s))
+        })
+    ) {
+        let _globals = lock();
+        let times = Times { shark_starve: 10_000, ..TIMES };
+        let mut sma = sma(width, height, &times, borderless, seed);
+        sma.gen_agents(0, sharks);
+
+        for _ in 0..20 {
+            let before = count(&sma, AgentKind::Shark);
+            let trace = sma.tick();
+            prop_assert_eq!(trace.shark_death, 0);
+            prop_assert_eq!(count(&sma, AgentKind::Shark), before + trace.shark_birth);
+        }
+    }
+
+    #[test]
+    fn walls_never_move((width, height, fish, sharks, borderless, seed) in board()) {
+        let _globals = lock();
+        let mut sma = populated(width, height, 0, 0, borderless, seed);
+        // One column of wall, the agents fill the rest
+        (0..height).for_each(|y| sma.environment.add_wall(Coord(width / 2, y)));
+        let free = (width * height - height) as u32;
+        sma.gen_agents(fish.min(free), sharks.min(free - fish.min(free)));
+        let expected = walls(&sma);
+
+        for _ in 0..10 {
+            sma.tick();
+            prop_assert_eq!(&walls(&sma), &expected);
+        }
+    }
+
+    #[test]
+    fn walled_shark_starves_after_its_starve_time(shark_starve in 0..30i32, seed in any::<u64>()) {
+        let _globals = lock();
+        let times = Times { shark_starve, ..TIMES };
+        // A single shark surrounded by walls cannot move, the count down is not spent twice
+        let mut sma = sma(3, 3, &times, false, seed);
+        (0..9)
+            .filter(|&idx| idx != 4)
+            .for_each(|idx| sma.environment.add_wall(Coord::from_idx(idx)));
+        sma.environment.spawn(AgentKind::Shark, Coord(1, 1));
+
+        for _ in 0..=shark_starve {
+            prop_assert_eq!(sma.tick().shark_death, 0);
+        }
+        prop_assert_eq!(sma.tick().shark_death, 1);
+        prop_assert_eq!(count(&sma, AgentKind::Shark), 0);
+    }
+}
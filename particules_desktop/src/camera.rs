@@ -0,0 +1,59 @@
+use nannou::geom::Rect;
+
+const MIN_ZOOM: f32 = 0.01;
+const MAX_ZOOM: f32 = 100.0;
+
+// World space is the board drawn with `cell_size` pixels per cell, centred on the origin.
+// The camera maps it to window space: screen = world * zoom + offset
+pub struct Camera {
+    pub zoom: f32,
+    pub offset_x: f32,
+    pub offset_y: f32,
+}
+
+impl Camera {
+    pub fn new() -> Camera {
+        Camera {
+            zoom: 1.0,
+            offset_x: 0.0,
+            offset_y: 0.0,
+        }
+    }
+
+    pub fn to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        (x * self.zoom + self.offset_x, y * self.zoom + self.offset_y)
+    }
+
+    pub fn to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            (x - self.offset_x) / self.zoom,
+            (y - self.offset_y) / self.zoom,
+        )
+    }
+
+    pub fn scale(&self, length: f32) -> f32 {
+        length * self.zoom
+    }
+
+    // Zoom by `factor` while keeping the world point under (x, y) still
+    pub fn zoom_at(&mut self, factor: f32, x: f32, y: f32) {
+        let (world_x, world_y) = self.to_world(x, y);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset_x = x - world_x * self.zoom;
+        self.offset_y = y - world_y * self.zoom;
+    }
+
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        self.offset_x += dx;
+        self.offset_y += dy;
+    }
+
+    // Center a `width` x `height` world rect and scale it to the window
+    pub fn fit(&mut self, window: Rect, width: f32, height: f32) {
+        self.zoom = (window.w() / width)
+            .min(window.h() / height)
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset_x = 0.0;
+        self.offset_y = 0.0;
+    }
+}
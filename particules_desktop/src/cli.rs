@@ -0,0 +1,96 @@
+use std::path::{Path, PathBuf};
+
+use particule_lib::config::{Config, ConfigError};
+use structopt::StructOpt;
+
+const DEFAULT_CONFIG: &str = "config.json";
+
+// Command line flags, each of them overrides the matching config file value
+#[derive(Debug, StructOpt)]
+#[structopt(name = "particules_desktop")]
+pub struct Opt {
+    /// Configuration or scenario file (json, toml or yaml), defaults to `config.json`
+    /// in the current or the crate directory
+    #[structopt(short, long, parse(from_os_str))]
+    pub config: Option<PathBuf>,
+
+    /// Scenario to run from a scenario file
+    #[structopt(short, long)]
+    pub scenario: Option<String>,
+
+    /// Random seed, the same seed and configuration always give the same run
+    #[structopt(long)]
+    pub seed: Option<u64>,
+
+    /// Number of columns
+    #[structopt(long)]
+    pub width: Option<u32>,
+
+    /// Number of rows
+    #[structopt(long)]
+    pub height: Option<u32>,
+
+    /// Initial number of fish
+    #[structopt(long)]
+    pub fish: Option<u32>,
+
+    /// Initial number of sharks
+    #[structopt(long)]
+    pub sharks: Option<u32>,
+
+    /// Pause the simulation after this many ticks
+    #[structopt(long)]
+    pub ticks: Option<u64>,
+
+    /// Start paused
+    #[structopt(long)]
+    pub paused: bool,
+}
+
+impl Opt {
+    pub fn config_path(&self) -> PathBuf {
+        if let Some(path) = &self.config {
+            return path.clone();
+        }
+
+        // Allow `cargo run` from anywhere in the workspace
+        let crate_config = Path::new(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_CONFIG);
+        if !Path::new(DEFAULT_CONFIG).exists() && crate_config.exists() {
+            crate_config
+        } else {
+            DEFAULT_CONFIG.into()
+        }
+    }
+
+    fn apply(&self, config: &mut Config) {
+        if let Some(seed) = self.seed {
+            config.seed = Some(seed);
+        }
+        if let Some(width) = self.width {
+            config.x = width as f32;
+        }
+        if let Some(height) = self.height {
+            config.y = height as f32;
+        }
+        if let Some(fish) = self.fish {
+            config.fish_number = fish;
+        }
+        if let Some(sharks) = self.sharks {
+            config.shark_number = sharks;
+        }
+        if let Some(ticks) = self.ticks {
+            config.ticks = Some(ticks);
+        }
+        if self.paused {
+            config.paused = true;
+        }
+    }
+
+    // Read the config file, apply the command line overrides and validate the result
+    pub fn load_config(&self) -> Result<Config, ConfigError> {
+        Config::from_file(self.config_path(), self.scenario.as_deref()).and_then(|mut config| {
+            self.apply(&mut config);
+            config.validate().map(|_| config)
+        })
+    }
+}
@@ -1,128 +1,534 @@
 #[macro_use]
 extern crate lazy_static;
 
-extern crate serde;
-#[macro_use]
-extern crate serde_derive;
-
-use std::fs::File;
-use std::io::prelude::*;
+use std::cell::{Ref, RefCell};
+use std::path::Path;
+use std::process;
 
-use nannou::event::Key;
+use nannou::event::{Key, MouseButton, MouseScrollDelta};
 use nannou::prelude::*;
-use particule_lib::AgentImpl;
-use std::convert::TryInto;
-
+use particule_lib::config::{Config, ExportConfig, ExportFormat};
+use particule_lib::environment::BLOCKED;
+use particule_lib::export::FrameExporter;
+use particule_lib::map::Map;
+use particule_lib::placement::PlacementError;
 use particule_lib::sma::Sma;
+use particule_lib::trace::{AgeStats, Stats, TraitStats};
+use particule_lib::{get_lifespan, is_juvenile, AgentKind, CellKind, Coord};
+use structopt::StructOpt;
+
+use crate::camera::Camera;
+use crate::cli::Opt;
+use crate::reload::ConfigWatcher;
+use crate::render::{Pixels, RenderMode, Trails};
+
+mod camera;
+mod cli;
+mod reload;
+mod render;
 
-mod user_config;
+// Written by the `S` key
+const MAP_FILE: &str = "map.txt";
+// Grid lines are hidden when cells get smaller than this on screen
+const MIN_GRID_CELL_SIZE: f32 = 4.0;
+// Size of the hover inspector panel in the bottom left corner, it grows with its lines
+const INSPECTOR_WIDTH: f32 = 380.0;
+const INSPECTOR_LINE_HEIGHT: f32 = 18.0;
 
 lazy_static! {
-    pub static ref CONFIG: user_config::Config = {
-        let mut file = File::open("config.json").unwrap();
-        let mut contents = String::new();
-        file.read_to_string(&mut contents).unwrap();
-        serde_json::from_str(&contents).expect("expected json")
-    };
+    static ref OPT: Opt = Opt::from_args();
+    // Config at startup, it is read again from `OPT` when the file changes
+    static ref CONFIG: Config = OPT.load_config().unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    });
 }
 
 fn main() {
+    // Report config errors before opening the window
+    lazy_static::initialize(&CONFIG);
     nannou::app(model).update(update).run();
 }
 
 struct Grid {
+    config: Config,
     sma: Sma,
+    mode: RenderMode,
+    show_grid: bool,
+    trails: Trails,
+    pixels: Pixels,
+    texture: wgpu::Texture,
+    tick: u64,
+    // Set while ticks are being recorded to image files
+    exporter: Option<FrameExporter>,
+    // Inspector statistics and the tick they were computed at
+    stats: RefCell<Option<(u64, Stats)>>,
 }
 
 impl Grid {
-    fn new() -> Self {
-        let mut sma = Sma::new(
-            CONFIG.x as i32,
-            CONFIG.y as i32,
-            CONFIG.fish_breed_time,
-            CONFIG.shark_breed_time,
-            CONFIG.shark_starve_time,
-            CONFIG.borderless,
+    fn new(device: &wgpu::Device, config: Config) -> Result<Self, PlacementError> {
+        let sma = Sma::from_config(&config)?;
+        let mut trails = Trails::new(sma.environment.size());
+        trails.update(&sma.environment.kinds);
+
+        let width = sma.environment.width as u32;
+        let height = sma.environment.height as u32;
+        let texture = wgpu::TextureBuilder::new()
+            .size([width, height])
+            .format(wgpu::TextureFormat::Rgba8Unorm)
+            .usage(wgpu::TextureUsage::COPY_DST | wgpu::TextureUsage::SAMPLED)
+            .build(device);
+
+        let mut grid = Grid {
+            show_grid: config.grid,
+            config,
+            sma,
+            mode: RenderMode::Species,
+            trails,
+            pixels: Pixels::new(width as usize, height as usize),
+            texture,
+            tick: 0,
+            exporter: None,
+            stats: RefCell::new(None),
+        };
+        grid.refresh();
+        Ok(grid)
+    }
+
+    fn tick(&mut self) {
+        let trace = self.sma.tick();
+        println!("{}", trace);
+        self.tick += 1;
+        self.trails.update(&self.sma.environment.kinds);
+        self.refresh();
+
+        if let Some(exporter) = &mut self.exporter {
+            if let Err(err) = exporter.capture(self.tick, &self.sma.environment) {
+                eprintln!("Frame export failed, recording stopped: {}", err);
+                self.exporter = None;
+            }
+        }
+    }
+
+    // Start recording with the `export` config section, or to `frames/*.png` without one
+    fn toggle_recording(&mut self) {
+        if let Some(exporter) = self.exporter.take() {
+            println!("Recording stopped, {} frames written", exporter.frames);
+            return;
+        }
+
+        let export = self.config.export.clone().unwrap_or(ExportConfig {
+            format: ExportFormat::Png,
+            path: "frames".into(),
+            every: 1,
+            scale: 1,
+        });
+
+        match FrameExporter::new(&export, &self.sma.environment) {
+            Ok(exporter) => {
+                println!("Recording to {}", export.path);
+                self.exporter = Some(exporter);
+            }
+            Err(err) => eprintln!("Unable to record to {}: {}", export.path, err),
+        }
+    }
+
+    // Apply the settings a running simulation can pick up
+    fn apply_parameters(&mut self, config: &Config) {
+        if config.grid != self.config.grid {
+            self.show_grid = config.grid;
+        }
+
+        reload::apply_parameters(&mut self.config, config);
+        self.sma.set_parameters(
+            config.fish_breed_time,
+            config.shark_breed_time,
+            config.shark_starve_time,
+            config.borderless,
         );
-        sma.gen_agents_grouped(CONFIG.fish_number, CONFIG.shark_number);
-        Grid { sma }
+        self.sma.set_colors(config.fish_color, config.shark_color);
+        self.sma.set_prey_policy(config.prey_policy);
+        self.sma.set_breeding(
+            config.breeding_of(AgentKind::Fish),
+            config.breeding_of(AgentKind::Shark),
+        );
+        self.sma
+            .set_lifespans(config.fish_lifespan, config.shark_lifespan);
+        self.sma.set_evolution(config.evolution);
+        if let Err(err) = self.sma.set_habitat(&config.habitat) {
+            eprintln!("Habitat not reloaded, {}", err);
+        }
+        // Juveniles depend on the breeding rules
+        *self.stats.get_mut() = None;
+        self.refresh();
+    }
+
+    fn next_mode(&mut self) {
+        self.mode = self.mode.next();
+        self.refresh();
+    }
+
+    // Recompute the texture content, it is uploaded on the next frame
+    fn refresh(&mut self) {
+        self.pixels
+            .fill(&self.sma.environment, self.mode, &self.trails);
+    }
+
+    // The board size comes from the map when there is one
+    fn world_size(&self) -> (f32, f32) {
+        (
+            self.sma.environment.width as f32 * self.config.cell_size,
+            self.sma.environment.height as f32 * self.config.cell_size,
+        )
+    }
+
+    // Cell under the window position (x, y), the board y axis goes up like the window one
+    fn cell_at(&self, camera: &Camera, x: f32, y: f32) -> Option<Coord> {
+        let (width, height) = self.world_size();
+        let (x, y) = camera.to_world(x, y);
+        let column = ((x + width / 2.0) / self.config.cell_size).floor();
+        let row = ((y + height / 2.0) / self.config.cell_size).floor();
+        let inside = column >= 0.0
+            && row >= 0.0
+            && column < self.sma.environment.width as f32
+            && row < self.sma.environment.height as f32;
+        if inside {
+            Some(Coord(column as i32, row as i32))
+        } else {
+            None
+        }
+    }
+
+    // Only gone over while the inspector is shown, once per tick: a paused simulation
+    // hovered for a while reuses them
+    fn stats(&self) -> Ref<'_, Stats> {
+        let stale = match &*self.stats.borrow() {
+            Some((tick, _)) => *tick != self.tick,
+            None => true,
+        };
+        if stale {
+            *self.stats.borrow_mut() = Some((self.tick, self.sma.stats()));
+        }
+        Ref::map(self.stats.borrow(), |stats| &stats.as_ref().unwrap().1)
+    }
+
+    // What the cell on `coord` holds, with the age distribution of both species
+    fn describe(&self, coord: Coord) -> String {
+        let environment = &self.sma.environment;
+        let idx = coord.as_idx();
+        let kind = environment.kinds[idx];
+        let mut lines = vec![format!("{:?} ({}, {})", kind, coord.0, coord.1)];
+
+        if let Some(agent_kind) = kind.agent() {
+            let age = environment.ages[idx];
+            let stage = if is_juvenile(agent_kind, age) {
+                "juvenile"
+            } else {
+                "adult"
+            };
+            let lifespan = match get_lifespan(agent_kind) {
+                Some(lifespan) => format!("lifespan {}", lifespan),
+                None => "no lifespan".into(),
+            };
+            lines.push(format!("age {}, {}, {}", age, stage, lifespan));
+            let breeding = describe_breeding(environment.breed_count_downs[idx]);
+            lines.push(match kind {
+                CellKind::Shark => format!(
+                    "{}, starve in {}",
+                    breeding, environment.starve_count_downs[idx]
+                ),
+                _ => breeding,
+            });
+            let traits = environment.traits[idx];
+            lines.push(format!(
+                "breed x{:.2}, starve x{:.2}, vision {}, speed {}",
+                traits.breed, traits.starve, traits.vision, traits.speed
+            ));
+        }
+
+        let stats = self.stats();
+        lines.push(describe_ages("Fish", &stats.fish_ages));
+        lines.push(describe_ages("Sharks", &stats.shark_ages));
+        if let Some(traits) = &stats.fish_traits {
+            lines.push(describe_traits("Fish", traits));
+        }
+        if let Some(traits) = &stats.shark_traits {
+            lines.push(describe_traits("Sharks", traits));
+        }
+        lines.join("\n")
     }
 
-    // This is the easy part, just draw the cells fill white if 1, black if 0
-    fn display(&self, draw: &app::Draw) {
-        let offset = CONFIG.cell_size;
+    fn save_map(&self) {
+        let path = Path::new(MAP_FILE);
+        match Map::from_environment(&self.sma.environment).write(path) {
+            Ok(()) => println!("Board saved to {}", MAP_FILE),
+            Err(err) => eprintln!("Unable to save the board to {}: {}", MAP_FILE, err),
+        }
+    }
+
+    // The whole board is a single texture, one texel per cell
+    // Everything goes through the camera so overlays stay aligned with the board
+    fn display(
+        &self,
+        device: &wgpu::Device,
+        draw: &Draw,
+        frame: &Frame,
+        camera: &Camera,
+        window: &Rect,
+    ) {
+        let (width, height) = self.world_size();
+        let (x, y) = camera.to_screen(0.0, 0.0);
+        let w = camera.scale(width);
+        let h = camera.scale(height);
 
-        let width = CONFIG.x * offset;
-        let height = CONFIG.y * offset;
         draw.rect()
-            .w_h(width, height)
+            .x_y(x, y)
+            .w_h(w, h)
             .rgb(1.0, 1.0, 1.0)
             .stroke(rgb(0.0, 0.0, 0.0));
 
-        self.sma.environment.board.iter().for_each(|cell| {
-            if !cell.is_empty() {
-                let agent: AgentImpl = cell.clone().try_into().unwrap();
-                let x = agent.coordinate().0 as f32;
-                let y = agent.coordinate().1 as f32;
-                let x = (x * CONFIG.cell_size) - width / 2.0 + offset / 2.0;
-                let y = (y * CONFIG.cell_size) - height / 2.0 + offset / 2.0;
-                self.display_agent(&draw, agent.get_color(), x, y);
+        let mut encoder = frame.command_encoder();
+        self.texture
+            .upload_data(device, &mut encoder, &self.pixels.data);
+
+        // Keep cells sharp when zooming in
+        let sampler = wgpu::SamplerBuilder::new()
+            .mag_filter(wgpu::FilterMode::Nearest)
+            .min_filter(wgpu::FilterMode::Nearest)
+            .into_descriptor();
+        draw.sampler(sampler)
+            .texture(&self.texture)
+            .x_y(x, y)
+            .w_h(w, h);
+
+        if self.show_grid {
+            self.display_grid_lines(draw, camera, window);
+        }
+    }
+
+    fn display_grid_lines(&self, draw: &Draw, camera: &Camera, window: &Rect) {
+        // Lines would cover the whole board below a few pixels per cell
+        if camera.scale(self.config.cell_size) < MIN_GRID_CELL_SIZE {
+            return;
+        }
+
+        let (width, height) = self.world_size();
+        let (left, bottom) = camera.to_screen(-width / 2.0, -height / 2.0);
+        let (right, top) = camera.to_screen(width / 2.0, height / 2.0);
+        let bottom_visible = bottom.max(window.bottom());
+        let top_visible = top.min(window.top());
+        let left_visible = left.max(window.left());
+        let right_visible = right.min(window.right());
+
+        (0..=self.sma.environment.width).for_each(|column| {
+            let (x, _) = camera.to_screen(column as f32 * self.config.cell_size - width / 2.0, 0.0);
+            if x >= window.left() && x <= window.right() {
+                draw.line()
+                    .start(pt2(x, bottom_visible))
+                    .end(pt2(x, top_visible))
+                    .weight(1.0)
+                    .rgb(0.8, 0.8, 0.8);
             }
         });
-    }
 
-    fn display_agent(&self, draw: &app::Draw, color: (f32, f32, f32), x: f32, y: f32) {
-        draw.rect()
-            .x_y(x, y)
-            .w_h(CONFIG.cell_size, CONFIG.cell_size)
-            .rgb(color.0, color.1, color.2);
+        (0..=self.sma.environment.height).for_each(|row| {
+            let (_, y) = camera.to_screen(0.0, row as f32 * self.config.cell_size - height / 2.0);
+            if y >= window.bottom() && y <= window.top() {
+                draw.line()
+                    .start(pt2(left_visible, y))
+                    .end(pt2(right_visible, y))
+                    .weight(1.0)
+                    .rgb(0.8, 0.8, 0.8);
+            }
+        });
     }
 }
 
 struct Model {
     pub grid: Grid,
+    pub camera: Camera,
     pub pause: bool,
     pub step: bool,
+    // Last cursor position while the left button is held down
+    pub drag: Option<(f32, f32)>,
+    // Cursor position while it is over the window, the cell under it is inspected
+    pub hover: Option<(f32, f32)>,
+    pub watcher: Option<ConfigWatcher>,
+    // Reloaded config waiting for a reset to change the board size or populations
+    pub pending: Option<Config>,
+}
+
+impl Model {
+    fn reload(&mut self) {
+        let config = match OPT.load_config() {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("Config not reloaded, {}", err);
+                return;
+            }
+        };
+
+        self.grid.apply_parameters(&config);
+        if reload::needs_reset(&self.grid.config, &config) {
+            println!("Config reloaded, press R to reset with the new board and populations");
+            self.pending = Some(config);
+        } else {
+            println!("Config reloaded");
+            self.pending = None;
+        }
+    }
+
+    fn reset(&mut self, app: &App) {
+        if let Some(config) = self.pending.take() {
+            let mode = self.grid.mode;
+            let window = app.main_window();
+            match Grid::new(window.swap_chain_device(), config) {
+                Ok(grid) => {
+                    self.grid = grid;
+                    self.grid.mode = mode;
+                    self.grid.refresh();
+                }
+                Err(err) => eprintln!("Board not reset, {}", err),
+            }
+        }
+    }
 }
 
 fn model(app: &App) -> Model {
-    app.new_window()
-        .with_maximized(true)
+    let window = app
+        .new_window()
+        .maximized(true)
         .event(window_event)
         .view(view)
         .build()
         .unwrap();
 
-    let grid = Grid::new();
+    let watcher = ConfigWatcher::new(&OPT.config_path())
+        .map_err(|err| eprintln!("Config changes won't be reloaded: {}", err))
+        .ok();
+
+    let window = app.window(window).unwrap();
+    let grid = Grid::new(window.swap_chain_device(), CONFIG.clone()).unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    });
     Model {
         grid,
-        pause: true,
+        camera: Camera::new(),
+        pause: CONFIG.paused,
         step: false,
+        drag: None,
+        hover: None,
+        watcher,
+        pending: None,
     }
 }
 
 fn update(_app: &App, model: &mut Model, _update: Update) {
+    if model.watcher.as_ref().is_some_and(ConfigWatcher::changed) {
+        model.reload();
+    }
+
     if !model.pause {
-        model.grid.sma.tick();
+        model.grid.tick();
     } else if model.step {
-        model.grid.sma.tick();
+        model.grid.tick();
         model.pause = true;
         model.step = false;
     }
+
+    if let Some(ticks) = model.grid.config.ticks {
+        if model.grid.tick == ticks {
+            model.pause = true;
+        }
+    }
 }
 
-fn view(app: &App, m: &Model, frame: &Frame) {
+fn view(app: &App, m: &Model, frame: Frame) {
     // Begin drawing
     let draw = app.draw();
     draw.background().color(rgb(1.0, 1.0, 1.0));
 
-    m.grid.display(&draw);
+    let window = app.main_window();
+    m.grid.display(
+        window.swap_chain_device(),
+        &draw,
+        &frame,
+        &m.camera,
+        &app.window_rect(),
+    );
+
+    if m.pending.is_some() {
+        display_reset_banner(&draw, &app.window_rect());
+    }
+
+    let hovered = m.hover.and_then(|(x, y)| m.grid.cell_at(&m.camera, x, y));
+    if let Some(coord) = hovered {
+        display_inspector(&draw, &app.window_rect(), &m.grid.describe(coord));
+    }
 
     // Write the result of our drawing to the window's frame.
     draw.to_frame(app, &frame).unwrap();
 }
 
-fn window_event(_: &App, model: &mut Model, event: WindowEvent) {
+// Screen space overlay, it doesn't move with the camera
+fn display_reset_banner(draw: &Draw, window: &Rect) {
+    let y = window.top() - 20.0;
+    draw.rect()
+        .x_y(0.0, y)
+        .w_h(window.w(), 40.0)
+        .rgba(1.0, 1.0, 0.6, 0.9);
+    draw.text("Config changed, press R to reset with the new board size and populations")
+        .x_y(0.0, y)
+        .w(window.w())
+        .font_size(16)
+        .rgb(0.0, 0.0, 0.0);
+}
+
+// Screen space overlay in the bottom left corner
+fn display_inspector(draw: &Draw, window: &Rect, text: &str) {
+    let height = text.lines().count() as f32 * INSPECTOR_LINE_HEIGHT + 20.0;
+    let x = window.left() + INSPECTOR_WIDTH / 2.0 + 10.0;
+    let y = window.bottom() + height / 2.0 + 10.0;
+    draw.rect()
+        .x_y(x, y)
+        .w_h(INSPECTOR_WIDTH, height)
+        .rgba(1.0, 1.0, 1.0, 0.9)
+        .stroke(rgb(0.0, 0.0, 0.0));
+    draw.text(text)
+        .x_y(x, y)
+        .w_h(INSPECTOR_WIDTH - 20.0, height - 20.0)
+        .font_size(14)
+        .left_justify()
+        .align_text_top()
+        .rgb(0.0, 0.0, 0.0);
+}
+
+// Blocked agents breeding in place wait for a free cell around them
+fn describe_breeding(breed_count_down: i32) -> String {
+    if breed_count_down == BLOCKED {
+        "breed once a cell around is free".into()
+    } else {
+        format!("breed in {}", breed_count_down)
+    }
+}
+
+fn describe_ages(species: &str, ages: &AgeStats) -> String {
+    format!(
+        "{}: mean age {:.1}, median {}, max {}, {} juveniles",
+        species, ages.mean, ages.median, ages.max, ages.juveniles
+    )
+}
+
+// Mean traits of a species
+fn describe_traits(species: &str, traits: &TraitStats) -> String {
+    format!(
+        "{}: breed x{:.2}, starve x{:.2}, vision {:.1}, speed {:.1}",
+        species, traits.breed.mean, traits.starve.mean, traits.vision.mean, traits.speed.mean
+    )
+}
+
+fn window_event(app: &App, model: &mut Model, event: WindowEvent) {
     match event {
         KeyPressed(key) => match key {
             Key::N => {
@@ -130,8 +536,38 @@ fn window_event(_: &App, model: &mut Model, event: WindowEvent) {
                 model.step = true;
             }
             Key::Space => model.pause = !model.pause,
+            Key::G => model.grid.show_grid = !model.grid.show_grid,
+            Key::M => model.grid.next_mode(),
+            Key::E => model.grid.toggle_recording(),
+            Key::R => model.reset(app),
+            Key::S => model.grid.save_map(),
+            Key::F => {
+                let (width, height) = model.grid.world_size();
+                model.camera.fit(app.window_rect(), width, height);
+            }
             _ => (),
         },
+        MouseWheel(delta, _) => {
+            let lines = match delta {
+                MouseScrollDelta::LineDelta(_, y) => y,
+                MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0,
+            };
+            let mouse = app.mouse.position();
+            model.camera.zoom_at(1.1f32.powf(lines), mouse.x, mouse.y);
+        }
+        MousePressed(MouseButton::Left) => {
+            let mouse = app.mouse.position();
+            model.drag = Some((mouse.x, mouse.y));
+        }
+        MouseReleased(MouseButton::Left) => model.drag = None,
+        MouseMoved(position) => {
+            model.hover = Some((position.x, position.y));
+            if let Some((x, y)) = model.drag {
+                model.camera.pan(position.x - x, position.y - y);
+                model.drag = Some((position.x, position.y));
+            }
+        }
+        MouseExited => model.hover = None,
         _ => {}
     }
 }
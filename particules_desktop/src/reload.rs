@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use particule_lib::config::Config;
+
+// Reports changes of the config file while the app runs
+pub struct ConfigWatcher {
+    path: PathBuf,
+    events: Receiver<DebouncedEvent>,
+    // Stops watching when dropped
+    _watcher: RecommendedWatcher,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path) -> notify::Result<ConfigWatcher> {
+        let path = path.canonicalize()?;
+        let (sender, events) = channel();
+        let mut watcher = watcher(sender, Duration::from_millis(200))?;
+
+        // Editors often replace the file rather than writing to it,
+        // watching the directory catches both
+        let directory = path.parent().unwrap_or_else(|| Path::new("."));
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+
+        Ok(ConfigWatcher {
+            path,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    // Has the file been written since the last call ? Drains all pending events
+    pub fn changed(&self) -> bool {
+        self.events.try_iter().fold(false, |changed, event| {
+            let written = match event {
+                DebouncedEvent::Write(path) | DebouncedEvent::Create(path) => path == self.path,
+                DebouncedEvent::Rename(_, to) => to == self.path,
+                _ => false,
+            };
+            changed || written
+        })
+    }
+}
+
+// Changes that can't be applied to a running simulation
+pub fn needs_reset(current: &Config, new: &Config) -> bool {
+    current.model != new.model
+        || current.x != new.x
+        || current.y != new.y
+        || current.placement != new.placement
+        || current.fish_placement != new.fish_placement
+        || current.shark_placement != new.shark_placement
+        || current.fish_number != new.fish_number
+        || current.shark_number != new.shark_number
+        || current.seed != new.seed
+        || current.map != new.map
+        || current.walls != new.walls
+        || current.fish_traits != new.fish_traits
+        || current.shark_traits != new.shark_traits
+}
+
+// Copy the settings a running simulation can pick up
+pub fn apply_parameters(current: &mut Config, new: &Config) {
+    current.cell_size = new.cell_size;
+    current.grid = new.grid;
+    current.borderless = new.borderless;
+    current.fish_breed_time = new.fish_breed_time;
+    current.shark_breed_time = new.shark_breed_time;
+    current.shark_starve_time = new.shark_starve_time;
+    current.prey_policy = new.prey_policy;
+    current.breeding = new.breeding;
+    current.fish_breeding = new.fish_breeding;
+    current.shark_breeding = new.shark_breeding;
+    current.fish_lifespan = new.fish_lifespan;
+    current.shark_lifespan = new.shark_lifespan;
+    current.evolution = new.evolution;
+    current.fish_color = new.fish_color;
+    current.shark_color = new.shark_color;
+    current.habitat = new.habitat.clone();
+    current.ticks = new.ticks;
+    current.export = new.export.clone();
+}
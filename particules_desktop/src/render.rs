@@ -0,0 +1,217 @@
+use particule_lib::environment::Environment;
+use particule_lib::get_fish_breed_time;
+use particule_lib::get_fish_color;
+use particule_lib::get_shark_breed_time;
+use particule_lib::get_shark_color;
+use particule_lib::to_rgba;
+use particule_lib::AgentKind;
+use particule_lib::CellKind;
+use particule_lib::Rgba;
+use particule_lib::WALL_COLOR;
+
+// How much of a trail survives each tick
+const TRAIL_DECAY: f32 = 0.85;
+// Side of the square window used for the density heat map is 2 * radius + 1
+const DENSITY_RADIUS: usize = 3;
+const BACKGROUND: Rgba = [255, 255, 255, 255];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderMode {
+    Species,
+    CountDown,
+    Density,
+    Trails,
+}
+
+impl RenderMode {
+    pub fn next(self) -> RenderMode {
+        match self {
+            RenderMode::Species => RenderMode::CountDown,
+            RenderMode::CountDown => RenderMode::Density,
+            RenderMode::Density => RenderMode::Trails,
+            RenderMode::Trails => RenderMode::Species,
+        }
+    }
+}
+
+// CPU side copy of the board texture, one RGBA pixel per cell
+pub struct Pixels {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+    colors: Vec<Rgba>,
+}
+
+impl Pixels {
+    pub fn new(width: usize, height: usize) -> Pixels {
+        Pixels {
+            width,
+            height,
+            data: vec![255; width * height * 4],
+            colors: vec![[0; 4]; width * height],
+        }
+    }
+
+    pub fn fill(&mut self, environment: &Environment, mode: RenderMode, trails: &Trails) {
+        let kinds = &environment.kinds;
+        match mode {
+            RenderMode::Species => environment.fill_colors(&mut self.colors),
+            RenderMode::CountDown => fill_with(&mut self.colors, |idx| {
+                count_down_color(kinds[idx], environment.breed_count_downs[idx])
+            }),
+            RenderMode::Density => {
+                let density = density(kinds, self.width, self.height);
+                fill_with(&mut self.colors, |idx| {
+                    if density[idx] > 0.0 {
+                        Some(heat(density[idx]))
+                    } else {
+                        None
+                    }
+                })
+            }
+            RenderMode::Trails => fill_with(&mut self.colors, |idx| trails.color(idx)),
+        }
+
+        // Walls look the same in every mode
+        kinds
+            .iter()
+            .zip(self.colors.iter_mut())
+            .filter(|(kind, _)| **kind == CellKind::Wall)
+            .for_each(|(_, color)| *color = WALL_COLOR);
+
+        // Texture rows go top to bottom while the board y axis goes up
+        let row_len = self.width * 4;
+        let colors = &self.colors;
+        let width = self.width;
+        let height = self.height;
+        self.data
+            .chunks_mut(row_len)
+            .enumerate()
+            .for_each(|(row, pixels)| {
+                let y = height - 1 - row;
+                pixels
+                    .chunks_mut(4)
+                    .zip(&colors[y * width..(y + 1) * width])
+                    .for_each(|(pixel, color)| {
+                        let color = if color[3] == 0 { &BACKGROUND } else { color };
+                        pixel.copy_from_slice(color)
+                    });
+            });
+    }
+}
+
+fn fill_with<F>(colors: &mut [Rgba], color_at: F)
+where
+    F: Fn(usize) -> Option<(f32, f32, f32)>,
+{
+    colors.iter_mut().enumerate().for_each(|(idx, color)| {
+        *color = color_at(idx).map(to_rgba).unwrap_or([0; 4]);
+    });
+}
+
+// Fading footprint of the agents that went through each cell
+pub struct Trails {
+    intensity: Vec<f32>,
+    colors: Vec<(f32, f32, f32)>,
+}
+
+impl Trails {
+    pub fn new(size: usize) -> Trails {
+        Trails {
+            intensity: vec![0.0; size],
+            colors: vec![(1.0, 1.0, 1.0); size],
+        }
+    }
+
+    pub fn update(&mut self, kinds: &[CellKind]) {
+        kinds.iter().enumerate().for_each(|(idx, kind)| {
+            if let Some(agent) = kind.agent() {
+                self.intensity[idx] = 1.0;
+                self.colors[idx] = agent_color(agent);
+            } else {
+                self.intensity[idx] *= TRAIL_DECAY;
+            }
+        });
+    }
+
+    pub fn color(&self, idx: usize) -> Option<(f32, f32, f32)> {
+        let intensity = self.intensity[idx];
+        if intensity < 0.05 {
+            None
+        } else {
+            Some(fade(self.colors[idx], intensity))
+        }
+    }
+}
+
+// Share of cells occupied by agents around each cell, computed with a summed area table
+pub fn density(kinds: &[CellKind], width: usize, height: usize) -> Vec<f32> {
+    let mut sums = vec![0u32; (width + 1) * (height + 1)];
+    for y in 0..height {
+        for x in 0..width {
+            let filled = kinds[y * width + x].agent().is_some() as u32;
+            sums[(y + 1) * (width + 1) + x + 1] =
+                filled + sums[y * (width + 1) + x + 1] + sums[(y + 1) * (width + 1) + x]
+                    - sums[y * (width + 1) + x];
+        }
+    }
+
+    let mut density = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let x0 = x.saturating_sub(DENSITY_RADIUS);
+            let y0 = y.saturating_sub(DENSITY_RADIUS);
+            let x1 = (x + DENSITY_RADIUS + 1).min(width);
+            let y1 = (y + DENSITY_RADIUS + 1).min(height);
+            let count = sums[y1 * (width + 1) + x1] + sums[y0 * (width + 1) + x0]
+                - sums[y0 * (width + 1) + x1]
+                - sums[y1 * (width + 1) + x0];
+            let area = ((x1 - x0) * (y1 - y0)) as f32;
+            density.push(count as f32 / area);
+        }
+    }
+
+    density
+}
+
+// Agent color, darker when the agent is far from breeding
+pub fn count_down_color(kind: CellKind, breed_count_down: i32) -> Option<(f32, f32, f32)> {
+    let agent = kind.agent()?;
+    let breed_time = match agent {
+        AgentKind::Fish => get_fish_breed_time(),
+        AgentKind::Shark => get_shark_breed_time(),
+    }
+    .max(1) as f32;
+
+    let progress = 1.0 - (breed_count_down as f32 / breed_time);
+    Some(fade(
+        agent_color(agent),
+        0.2 + 0.8 * progress.clamp(0.0, 1.0),
+    ))
+}
+
+fn agent_color(kind: AgentKind) -> (f32, f32, f32) {
+    match kind {
+        AgentKind::Fish => get_fish_color(),
+        AgentKind::Shark => get_shark_color(),
+    }
+}
+
+// Blue -> green -> red heat gradient, `t` in [0, 1]
+pub fn heat(t: f32) -> (f32, f32, f32) {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 {
+        (0.0, t * 2.0, 1.0 - t * 2.0)
+    } else {
+        ((t - 0.5) * 2.0, 1.0 - (t - 0.5) * 2.0, 0.0)
+    }
+}
+
+// Blend a color toward the white background
+fn fade(color: (f32, f32, f32), intensity: f32) -> (f32, f32, f32) {
+    (
+        1.0 - (1.0 - color.0) * intensity,
+        1.0 - (1.0 - color.1) * intensity,
+        1.0 - (1.0 - color.2) * intensity,
+    )
+}
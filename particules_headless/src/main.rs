@@ -0,0 +1,177 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use particule_lib::analysis;
+use particule_lib::config::{Config, ExportConfig, ExportFormat};
+use particule_lib::export::FrameExporter;
+use particule_lib::lotka_volterra;
+use particule_lib::map::Map;
+use particule_lib::sma::Sma;
+use particule_lib::trace::Populations;
+use structopt::StructOpt;
+
+// Run the simulation without a window, printing the tick traces then a summary of the run
+#[derive(Debug, StructOpt)]
+#[structopt(name = "particules_headless")]
+struct Opt {
+    /// Configuration or scenario file (json, toml or yaml)
+    #[structopt(short, long, default_value = "config.json", parse(from_os_str))]
+    config: PathBuf,
+
+    /// Scenario to run from a scenario file
+    #[structopt(short, long)]
+    scenario: Option<String>,
+
+    /// Number of ticks to run, 1000 unless set in the config file
+    #[structopt(short, long)]
+    ticks: Option<u64>,
+
+    /// Random seed, the same seed and configuration always give the same run
+    #[structopt(long)]
+    seed: Option<u64>,
+
+    /// Write every exported tick to numbered png files in this directory
+    #[structopt(long, parse(from_os_str), conflicts_with = "gif")]
+    png: Option<PathBuf>,
+
+    /// Write exported ticks to this animated gif
+    #[structopt(long, parse(from_os_str))]
+    gif: Option<PathBuf>,
+
+    /// Export one tick out of N
+    #[structopt(long)]
+    every: Option<u32>,
+
+    /// Size in pixels of a cell in exported images
+    #[structopt(long)]
+    scale: Option<u32>,
+
+    /// Start from this text or png map instead of the config board and populations
+    #[structopt(long)]
+    map: Option<String>,
+
+    /// Save the board to this text or png map after the last tick
+    #[structopt(long, parse(from_os_str))]
+    save_map: Option<PathBuf>,
+
+    /// Fit the Lotka-Volterra equations to the run and write both series to this csv file
+    #[structopt(long, parse(from_os_str))]
+    lotka_volterra: Option<PathBuf>,
+}
+
+impl Opt {
+    // Command line export flags take precedence over the config file
+    fn export_config(&self, config: &Config) -> Option<ExportConfig> {
+        let mut export = if let Some(path) = &self.png {
+            Some(ExportConfig {
+                format: ExportFormat::Png,
+                path: path.to_string_lossy().into(),
+                every: 1,
+                scale: 1,
+            })
+        } else if let Some(path) = &self.gif {
+            Some(ExportConfig {
+                format: ExportFormat::Gif,
+                path: path.to_string_lossy().into(),
+                every: 1,
+                scale: 1,
+            })
+        } else {
+            config.export.clone()
+        };
+
+        if let Some(export) = &mut export {
+            if let Some(every) = self.every {
+                export.every = every;
+            }
+            if let Some(scale) = self.scale {
+                export.scale = scale;
+            }
+        }
+
+        export
+    }
+}
+
+fn main() {
+    let opt = Opt::from_args();
+
+    let config = Config::from_file(&opt.config, opt.scenario.as_deref()).and_then(|mut config| {
+        if opt.seed.is_some() {
+            config.seed = opt.seed;
+        }
+        if opt.ticks.is_some() {
+            config.ticks = opt.ticks;
+        }
+        if opt.map.is_some() {
+            config.map = opt.map.clone();
+        }
+        config.export = opt.export_config(&config);
+        config.validate().map(|_| config)
+    });
+    let config = config.unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    });
+
+    let mut sma = Sma::from_config(&config).unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    });
+
+    let mut exporter = config.export.as_ref().map(|export| {
+        FrameExporter::new(export, &sma.environment).unwrap_or_else(|err| {
+            eprintln!("error: unable to export to {}: {}", export.path, err);
+            process::exit(1);
+        })
+    });
+
+    let mut populations = Populations::default();
+    populations.record(&sma.environment);
+
+    let ticks = config.ticks.unwrap_or(1000);
+    for tick in 1..=ticks {
+        let trace = sma.tick();
+        populations.record(&sma.environment);
+        println!("{}, {}", trace, sma.stats());
+        // Frame `tick` is the board once tick `tick` is done, as in the desktop app
+        if let Some(exporter) = &mut exporter {
+            if let Err(err) = exporter.capture(tick, &sma.environment) {
+                eprintln!("error: unable to export tick {}: {}", tick, err);
+                process::exit(1);
+            }
+        }
+    }
+    println!("{}", analysis::analyze(&populations));
+
+    if let Some(path) = &opt.lotka_volterra {
+        compare_to_lotka_volterra(&populations, path);
+    }
+
+    if let Some(path) = &opt.save_map {
+        if let Err(err) = Map::from_environment(&sma.environment).write(path) {
+            eprintln!("error: unable to save map {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    }
+}
+
+// Print the fitted parameters and write the recorded and modelled populations
+fn compare_to_lotka_volterra(populations: &Populations, path: &Path) {
+    let comparison = match lotka_volterra::compare(populations) {
+        Some(comparison) => comparison,
+        None => {
+            eprintln!("error: lotka-volterra needs both species at the start and 2 ticks at least");
+            process::exit(1);
+        }
+    };
+    println!("lotka-volterra: {}", comparison);
+
+    let written = File::create(path).and_then(|file| comparison.write_csv(BufWriter::new(file)));
+    if let Err(err) = written {
+        eprintln!("error: unable to write {}: {}", path.display(), err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,181 @@
+use std::io::prelude::*;
+use std::io::stdout;
+use std::panic;
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::{cursor, execute, terminal};
+use particule_lib::config::Config;
+use particule_lib::sma::Sma;
+use structopt::StructOpt;
+
+use crate::screen::{Status, Viewport};
+
+mod screen;
+
+// Ticks per second for each speed level, `None` runs as fast as possible
+const SPEEDS: [Option<u32>; 7] = [
+    Some(1),
+    Some(2),
+    Some(5),
+    Some(10),
+    Some(20),
+    Some(50),
+    None,
+];
+// Keep the terminal responsive when running at full speed
+const FRAME_TIME: Duration = Duration::from_millis(33);
+
+// Watch the simulation in a terminal
+#[derive(Debug, StructOpt)]
+#[structopt(name = "particules_tui")]
+struct Opt {
+    /// Configuration or scenario file (json, toml or yaml)
+    #[structopt(short, long, default_value = "config.json", parse(from_os_str))]
+    config: PathBuf,
+
+    /// Scenario to run from a scenario file
+    #[structopt(short, long)]
+    scenario: Option<String>,
+}
+
+struct Model {
+    sma: Sma,
+    viewport: Viewport,
+    tick: u64,
+    pause: bool,
+    step: bool,
+    speed: usize,
+    quit: bool,
+}
+
+impl Model {
+    fn status(&self) -> Status {
+        Status {
+            tick: self.tick,
+            paused: self.pause,
+            ticks_per_second: SPEEDS[self.speed],
+        }
+    }
+
+    fn tick(&mut self) {
+        self.sma.tick();
+        self.tick += 1;
+    }
+
+    fn key_pressed(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+            KeyCode::Char(' ') => self.pause = !self.pause,
+            KeyCode::Char('n') => {
+                self.pause = true;
+                self.step = true;
+            }
+            KeyCode::Char('+') => self.speed = (self.speed + 1).min(SPEEDS.len() - 1),
+            KeyCode::Char('-') => self.speed = self.speed.saturating_sub(1),
+            KeyCode::Left => self.viewport.pan(-4, 0, &self.sma.environment),
+            KeyCode::Right => self.viewport.pan(4, 0, &self.sma.environment),
+            KeyCode::Up => self.viewport.pan(0, -4, &self.sma.environment),
+            KeyCode::Down => self.viewport.pan(0, 4, &self.sma.environment),
+            _ => (),
+        }
+    }
+}
+
+fn main() {
+    let opt = Opt::from_args();
+
+    let config = Config::from_file(&opt.config, opt.scenario.as_deref())
+        .and_then(|config| config.validate().map(|_| config));
+    let config = config.unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    });
+
+    let sma = Sma::from_config(&config).unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    });
+
+    let mut model = Model {
+        sma,
+        viewport: Viewport::new(),
+        tick: 0,
+        pause: true,
+        step: false,
+        speed: 3,
+        quit: false,
+    };
+
+    // A panic message is unreadable in raw mode on the alternate screen
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+
+    let mut out = stdout();
+    let result = terminal::enable_raw_mode()
+        .and_then(|_| execute!(out, terminal::EnterAlternateScreen, cursor::Hide))
+        .and_then(|_| run(&mut model, &mut out));
+
+    restore_terminal();
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+// Give the terminal back to the shell, whatever state the simulation left it in
+fn restore_terminal() {
+    let _ = execute!(stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+}
+
+fn run<W: Write>(model: &mut Model, out: &mut W) -> crossterm::Result<()> {
+    let mut last_tick = Instant::now();
+    execute!(out, terminal::Clear(terminal::ClearType::All))?;
+
+    while !model.quit {
+        screen::draw(
+            out,
+            &model.sma.environment,
+            &model.viewport,
+            &model.status(),
+        )?;
+
+        let tick_delay = match SPEEDS[model.speed] {
+            Some(ticks) => Duration::from_millis(1000 / ticks as u64),
+            None => Duration::from_millis(0),
+        };
+        let timeout = if model.pause {
+            FRAME_TIME
+        } else {
+            tick_delay
+                .checked_sub(last_tick.elapsed())
+                .unwrap_or_default()
+                .min(FRAME_TIME)
+        };
+
+        if event::poll(timeout)? {
+            match event::read()? {
+                Event::Key(key) => model.key_pressed(key),
+                Event::Resize(_, _) => execute!(out, terminal::Clear(terminal::ClearType::All))?,
+                _ => (),
+            }
+        }
+
+        if model.step {
+            model.tick();
+            model.step = false;
+        } else if !model.pause && last_tick.elapsed() >= tick_delay {
+            model.tick();
+            last_tick = Instant::now();
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use std::io::Write;
+
+use crossterm::style::{Color, Print, SetBackgroundColor, SetForegroundColor};
+use crossterm::{cursor, queue, terminal};
+use particule_lib::environment::Environment;
+use particule_lib::{AgentKind, Rgba};
+
+// Each character shows two stacked cells: the upper one as the foreground
+// of a half block, the lower one as its background
+const UPPER_HALF_BLOCK: char = '▀';
+const LOWER_HALF_BLOCK: char = '▄';
+
+pub struct Status {
+    pub tick: u64,
+    pub paused: bool,
+    pub ticks_per_second: Option<u32>,
+}
+
+// Part of the board shown in the terminal, in cells
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Viewport {
+    pub fn new() -> Viewport {
+        Viewport { x: 0, y: 0 }
+    }
+
+    pub fn pan(&mut self, dx: i32, dy: i32, environment: &Environment) {
+        let (columns, rows) = terminal::size().unwrap_or((80, 24));
+        let max_x = (environment.width - columns as i32).max(0);
+        let max_y = (environment.height - board_rows(rows) as i32).max(0);
+        self.x = (self.x + dx).max(0).min(max_x);
+        self.y = (self.y + dy).max(0).min(max_y);
+    }
+}
+
+// Cells that fit in the terminal, the last line is kept for the status bar
+fn board_rows(terminal_rows: u16) -> u16 {
+    terminal_rows.saturating_sub(1) * 2
+}
+
+pub fn draw<W: Write>(
+    out: &mut W,
+    environment: &Environment,
+    viewport: &Viewport,
+    status: &Status,
+) -> crossterm::Result<()> {
+    let (columns, rows) = terminal::size()?;
+    let colors = environment.colors();
+    let width = environment.width;
+    let height = environment.height;
+    let visible_columns = (columns as i32).min(width - viewport.x);
+    let visible_rows = (board_rows(rows) as i32).min(height - viewport.y);
+
+    // Board y axis goes up, like in the desktop app
+    let color_at = |x: i32, y: i32| -> Color {
+        if y < 0 || y >= visible_rows {
+            return Color::Reset;
+        }
+        let board_y = height - 1 - (viewport.y + y);
+        let color = colors[(board_y * width + viewport.x + x) as usize];
+        to_color(color)
+    };
+
+    let mut current = (Color::Reset, Color::Reset);
+    queue!(
+        out,
+        SetForegroundColor(current.0),
+        SetBackgroundColor(current.1)
+    )?;
+
+    for line in 0..(visible_rows + 1) / 2 {
+        queue!(out, cursor::MoveTo(0, line as u16))?;
+        for x in 0..visible_columns {
+            let (glyph, colors) = match (color_at(x, line * 2), color_at(x, line * 2 + 1)) {
+                (Color::Reset, Color::Reset) => (' ', (current.0, Color::Reset)),
+                // The terminal default foreground would show up in the empty upper half
+                (Color::Reset, lower) => (LOWER_HALF_BLOCK, (lower, Color::Reset)),
+                (upper, lower) => (UPPER_HALF_BLOCK, (upper, lower)),
+            };
+            if colors.0 != current.0 {
+                queue!(out, SetForegroundColor(colors.0))?;
+            }
+            if colors.1 != current.1 {
+                queue!(out, SetBackgroundColor(colors.1))?;
+            }
+            current = colors;
+            queue!(out, Print(glyph))?;
+        }
+    }
+
+    queue!(
+        out,
+        SetForegroundColor(Color::Reset),
+        SetBackgroundColor(Color::Reset),
+        cursor::MoveTo(0, rows.saturating_sub(1)),
+        terminal::Clear(terminal::ClearType::CurrentLine),
+        Print(status_line(environment, status))
+    )?;
+
+    out.flush()?;
+    Ok(())
+}
+
+fn status_line(environment: &Environment, status: &Status) -> String {
+    let speed = match status.ticks_per_second {
+        Some(ticks) => format!("{} ticks/s", ticks),
+        None => "max speed".into(),
+    };
+
+    format!(
+        "tick {} | fish {} | sharks {} | {} | {} | space: pause  n: step  +/-: speed  arrows: scroll  q: quit",
+        status.tick,
+        environment.count(AgentKind::Fish),
+        environment.count(AgentKind::Shark),
+        if status.paused { "paused" } else { "running" },
+        speed,
+    )
+}
+
+fn to_color(color: Rgba) -> Color {
+    if color[3] == 0 {
+        Color::Reset
+    } else {
+        Color::Rgb {
+            r: color[0],
+            g: color[1],
+            b: color[2],
+        }
+    }
+}